@@ -61,35 +61,55 @@ export const EvmPayloadShape = M.splitRecord({
   ),
 });
 
-export const CosmosCreateSurveyShape = M.splitRecord({
+export const CosmosManagerSignatureShape = M.splitRecord({
+  pub_key: NonEmptyStringShape,
   signature: NonEmptyStringShape,
-  token: NonEmptyStringShape,
-  time_to_expire: M.number(),
-  owner: NonEmptyStringShape,
-  survey_id: NonEmptyStringShape,
-  participants_limit: M.and(M.number(), M.gte(1)),
-  reward_denom: NonEmptyStringShape,
-  reward_amount: M.and(M.number(), M.gte(0)),
-  survey_hash: NonEmptyStringShape,
-  manager_pub_key: NonEmptyStringShape,
 });
 
-export const CosmosCancelSurveyShape = M.splitRecord({
-  signature: NonEmptyStringShape,
-  token: NonEmptyStringShape,
-  time_to_expire: M.number(),
-  survey_id: NonEmptyStringShape,
-  manager_pub_key: NonEmptyStringShape,
+const CosmosManagerSignaturesShape = M.arrayOf(CosmosManagerSignatureShape, {
+  arrayLengthLimit: 20,
 });
 
-export const CosmosPayRewardsShape = M.splitRecord({
-  signature: NonEmptyStringShape,
-  token: NonEmptyStringShape,
-  time_to_expire: M.number(),
-  survey_ids: M.arrayOf(NonEmptyStringShape, { arrayLengthLimit: 100 }),
-  participants: M.arrayOf(NonEmptyStringShape, { arrayLengthLimit: 100 }),
-  manager_pub_key: NonEmptyStringShape,
-});
+const DomainVersionShape = M.or('v1', 'v2');
+
+export const CosmosCreateSurveyShape = M.splitRecord(
+  {
+    signatures: CosmosManagerSignaturesShape,
+    token: NonEmptyStringShape,
+    time_to_expire: M.number(),
+    owner: NonEmptyStringShape,
+    survey_id: NonEmptyStringShape,
+    participants_limit: M.and(M.number(), M.gte(1)),
+    reward_denom: NonEmptyStringShape,
+    reward_amount: M.and(M.number(), M.gte(0)),
+    survey_hash: NonEmptyStringShape,
+  },
+  {
+    ends_at: M.number(),
+    domain_version: DomainVersionShape,
+  },
+);
+
+export const CosmosCancelSurveyShape = M.splitRecord(
+  {
+    signatures: CosmosManagerSignaturesShape,
+    token: NonEmptyStringShape,
+    time_to_expire: M.number(),
+    survey_id: NonEmptyStringShape,
+  },
+  { domain_version: DomainVersionShape },
+);
+
+export const CosmosPayRewardsShape = M.splitRecord(
+  {
+    signatures: CosmosManagerSignaturesShape,
+    token: NonEmptyStringShape,
+    time_to_expire: M.number(),
+    survey_ids: M.arrayOf(NonEmptyStringShape, { arrayLengthLimit: 100 }),
+    participants: M.arrayOf(NonEmptyStringShape, { arrayLengthLimit: 100 }),
+  },
+  { domain_version: DomainVersionShape },
+);
 
 export const CosmosPayloadShape = M.splitRecord({
   msg: M.or(
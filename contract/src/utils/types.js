@@ -273,7 +273,19 @@ import '@agoric/vats/src/types.js';
 
 /**
  * @typedef {{
+ *   pub_key: string;
  *   signature: string;
+ * }} CosmosManagerSignature
+ */
+
+/**
+ * Proof format the managers signed, the contract reads a missing version as V1
+ * @typedef {'v1' | 'v2'} DomainVersion
+ */
+
+/**
+ * @typedef {{
+ *   signatures: CosmosManagerSignature[];
  *   token: string;
  *   time_to_expire: number;
  *   owner: string;
@@ -282,28 +294,29 @@ import '@agoric/vats/src/types.js';
  *   reward_denom: string;
  *   reward_amount: number;
  *   survey_hash: string;
- *   manager_pub_key: string;
+ *   ends_at?: number;
+ *   domain_version?: DomainVersion;
  * }} CosmosCreateSurvey
  */
 
 /**
  * @typedef {{
- *   signature: string;
+ *   signatures: CosmosManagerSignature[];
  *   token: string;
  *   time_to_expire: number;
  *   survey_id: string;
- *   manager_pub_key: string;
+ *   domain_version?: DomainVersion;
  * }} CosmosCancelSurvey
  */
 
 /**
  * @typedef {{
- *   signature: string;
+ *   signatures: CosmosManagerSignature[];
  *   token: string;
  *   time_to_expire: number;
  *   survey_ids: string[];
  *   participants: Bech32Address[];
- *   manager_pub_key: string;
+ *   domain_version?: DomainVersion;
  * }} CosmosPayRewards
  */
 
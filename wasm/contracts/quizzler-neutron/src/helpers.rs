@@ -1,174 +1,285 @@
-use crate::error::ContractError;
-use crate::msg::Manager;
-use crate::state::{ManagerInfo, CONFIG, MANAGERS, USED_PROOF_TOKENS};
+use crate::ibc_lifecycle::msg_with_sudo_callback;
+use crate::msg::{DenomFee, SudoPayload};
+use crate::state::{IbcFee, FEE_RESERVE, IBC_FEES};
 use cosmwasm_std::{
-    Addr, BalanceResponse, BankQuery, Binary, Deps, DepsMut, Env, QuerierWrapper, QueryRequest,
-    Uint128,
+    to_json_string, BankMsg, Coin, Deps, DepsMut, Env, IbcTimeoutBlock, MessageInfo, Order,
+    Response, StdResult, Storage, SubMsg, Uint128,
 };
+use cw_utils::PaymentError;
 use neutron_std::types::cosmos::base::v1beta1::Coin as StdCoin;
 use neutron_std::types::ibc::core::client::v1::Height;
 use neutron_std::types::neutron::feerefunder::Fee;
 use neutron_std::types::neutron::transfer::MsgTransfer;
+use quizzler_core::forward::{forwarding, PacketMemo};
+use quizzler_core::helpers::check_is_contract_owner;
+use quizzler_core::msg::Route;
+use quizzler_core::state::CONFIG;
+use quizzler_core::transfer::{self, Transfer, TransferAdapter};
+use quizzler_core::{execute, ContractError};
 
-const DEFAULT_TIMEOUT_HEIGHT: u64 = 10000000;
+/// Sends survey payouts through Neutron's `transfer` module, registering a sudo
+/// callback for every packet.
+pub struct IbcTransferAdapter;
 
-pub fn map_validate(
-    receiver_prefix: &str,
-    managers: &[Manager],
-) -> Result<Vec<ManagerInfo>, ContractError> {
-    managers
-        .iter()
-        .map(|admin| {
-            let (_, validated_addr) = validate_account(receiver_prefix, &admin.addr)?;
-            let pub_key = Binary::from_base64(&admin.pub_key)?;
-            Ok(ManagerInfo {
-                address: validated_addr,
-                pub_key: pub_key,
-                status: true,
-            })
-        })
-        .collect()
+impl TransferAdapter for IbcTransferAdapter {
+    fn create_transfer(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        transfer: Transfer,
+        route: Route,
+    ) -> Result<SubMsg, ContractError> {
+        // The relayer fee is escrowed from the fee reserve, never from the payout
+        let fee = IBC_FEES.may_load(deps.storage, &transfer.coin.denom)?;
+        if let Some(fee) = &fee {
+            charge_fee_reserve(deps.storage, fee)?;
+        }
+
+        let ibc_msg = create_ibc_transfer(
+            deps.as_ref(),
+            env,
+            transfer.recipient.as_str(),
+            &route,
+            &transfer.coin.denom,
+            transfer.coin.amount,
+            fee.clone(),
+        )?;
+
+        let submsg = msg_with_sudo_callback(deps, ibc_msg, SudoPayload::new(transfer, fee))?;
+
+        Ok(submsg)
+    }
 }
 
-pub fn validate_account(
-    receiver_prefix: &str,
+pub fn create_ibc_transfer(
+    deps: Deps,
+    env: &Env,
     receiver: &str,
-) -> Result<(String, Addr), ContractError> {
-    let Ok((prefix, _, _)) = bech32::decode(receiver) else {
-        return Err(ContractError::InvalidAccount {
-            receiver: receiver.to_string(),
-        });
+    route: &Route,
+    denom: &str,
+    amount: Uint128,
+    fee: Option<IbcFee>,
+) -> Result<MsgTransfer, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    // Multi-hop routes address the packet to the first hop, which forwards it on
+    let (receiver, memo) = match forwarding(route, receiver) {
+        Some(forwarding) => (
+            forwarding.receiver,
+            to_json_string(&PacketMemo {
+                forward: forwarding.forward,
+            })?,
+        ),
+        None => (receiver.to_string(), "".to_string()),
     };
 
-    if prefix != receiver_prefix {
-        return Err(ContractError::ExpectedAgoricAccount {
-            receiver: receiver.to_string(),
-        });
-    }
+    let coin = create_coin(denom, amount);
 
-    Ok((prefix, Addr::unchecked(receiver)))
-}
+    let timeout_height = config.ibc_timeout_block.map(|block| Height {
+        revision_number: block.revision,
+        revision_height: block.height,
+    });
 
-pub fn auth_validations(
-    deps: &mut DepsMut,
-    env: &Env,
-    token: String,
-    message: Binary,
-    pub_key: String,
-    time_to_expire: u64,
-    signature: String,
-) -> Result<(), ContractError> {
-    let pub_key = Binary::from_base64(&pub_key)?;
-    let signature = Binary::from_base64(&signature)?;
+    let msg = MsgTransfer {
+        source_port: "transfer".to_string(),
+        source_channel: route.channel_id.clone(),
+        token: Some(coin),
+        receiver,
+        timeout_height,
+        timeout_timestamp: env
+            .block
+            .time
+            .plus_seconds(config.ibc_timeout_seconds)
+            .nanos(),
+        fee: fee.map(Fee::from),
+        memo,
+        sender: env.contract.address.to_string(),
+    };
 
-    let current_block_time = env.block.time.seconds();
-    if time_to_expire < current_block_time {
-        return Err(ContractError::ProofExpired {});
-    }
+    Ok(msg)
+}
 
-    let proof_token_exists = USED_PROOF_TOKENS
-        .load(deps.storage, &token)
-        .unwrap_or(false);
+/// Owner only. Replaces the IBC timeouts and, when `ibc_fees` is set, every per-denom fee.
+pub fn update_config(
+    ctx: (DepsMut, &Env, MessageInfo),
+    ibc_timeout_seconds: u64,
+    ibc_timeout_block: Option<IbcTimeoutBlock>,
+    ibc_fees: Option<Vec<DenomFee>>,
+) -> Result<Response, ContractError> {
+    let (mut deps, env, info) = ctx;
 
-    if proof_token_exists {
-        return Err(ContractError::TokenAlreadyUsed {});
-    }
+    let response = execute::update_config(
+        (deps.branch(), env, info),
+        ibc_timeout_seconds,
+        ibc_timeout_block,
+    )?;
+
+    let Some(ibc_fees) = ibc_fees else {
+        return Ok(response);
+    };
 
-    // Verify pub key exists
-    let managers = MANAGERS
-        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
-        .collect::<Result<Vec<(Addr, ManagerInfo)>, cosmwasm_std::StdError>>()?;
+    let number_of_fees = ibc_fees.len();
+    set_ibc_fees(deps.storage, ibc_fees)?;
 
-    let mut pub_key_exists = false;
+    Ok(response.add_attribute("ibc_fees", number_of_fees.to_string()))
+}
 
-    for (_addr, manager_info) in managers.iter() {
-        if manager_info.pub_key == pub_key && manager_info.status {
-            pub_key_exists = true;
-            break;
-        }
+/// Replaces every configured fee with `ibc_fees`
+pub fn set_ibc_fees(
+    storage: &mut dyn Storage,
+    ibc_fees: Vec<DenomFee>,
+) -> Result<(), ContractError> {
+    let denoms = IBC_FEES
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for denom in denoms {
+        IBC_FEES.remove(storage, &denom);
     }
 
-    if !pub_key_exists {
-        return Err(ContractError::InvalidSigner {});
-    }
+    for DenomFee {
+        denom,
+        ack_fee,
+        timeout_fee,
+    } in ibc_fees
+    {
+        // Neutron's feerefunder requires both an ack and a timeout fee
+        if ack_fee.is_empty() || timeout_fee.is_empty() {
+            return Err(ContractError::InvalidConfig {
+                reason: format!("ack_fee and timeout_fee must be set for {denom}"),
+            });
+        }
 
-    let result = deps.api.ed25519_verify(&message, &signature, &pub_key)?;
+        if let Some(coin) = ack_fee
+            .iter()
+            .chain(timeout_fee.iter())
+            .find(|coin| coin.denom.is_empty() || coin.amount.is_zero())
+        {
+            return Err(ContractError::InvalidConfig {
+                reason: format!("invalid fee coin {coin} for {denom}"),
+            });
+        }
 
-    // mark proof token as used
-    USED_PROOF_TOKENS.save(deps.storage, &token, &true)?;
+        if IBC_FEES.has(storage, &denom) {
+            return Err(ContractError::InvalidConfig {
+                reason: format!("duplicate fee for {denom}"),
+            });
+        }
 
-    if !result {
-        return Err(ContractError::InvalidMessageHash {});
+        IBC_FEES.save(
+            storage,
+            &denom,
+            &IbcFee {
+                ack_fee,
+                timeout_fee,
+            },
+        )?;
     }
 
     Ok(())
 }
 
-pub fn check_is_contract_owner(deps: Deps, sender: Addr) -> Result<(), ContractError> {
-    let config = CONFIG.load(deps.storage).unwrap();
-    if config.owner != sender {
-        Err(ContractError::Unauthorized {})
-    } else {
-        Ok(())
+/// Owner only. Adds the attached funds to the fee reserve.
+pub fn fund_fee_reserve(ctx: (DepsMut, &Env, MessageInfo)) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    if info.funds.is_empty() {
+        return Err(PaymentError::NoFunds {}.into());
     }
-}
 
-pub fn query_contract_balance(
-    querier: &QuerierWrapper,
-    addr: &Addr,
-    denom: &str,
-) -> Result<Uint128, ContractError> {
-    let resp: BalanceResponse = querier.query(&QueryRequest::Bank(BankQuery::Balance {
-        address: addr.to_string(),
-        denom: denom.to_string(),
-    }))?;
-    Ok(resp.amount.amount)
+    credit_fee_reserve(deps.storage, &info.funds)?;
+
+    let funded = info
+        .funds
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Ok(Response::new()
+        .add_attribute("action", "fund_fee_reserve")
+        .add_attribute("amount", funded))
 }
 
-pub fn create_ibc_transfer(
-    deps: Deps,
-    env: &Env,
-    receiver: &str,
-    denom: &str,
-    amount: Uint128,
-) -> Result<MsgTransfer, ContractError> {
-    let config = CONFIG.load(deps.storage).unwrap();
+/// Owner only. Sends `amount` out of the fee reserve to `recipient`.
+pub fn withdraw_fee_reserve(
+    ctx: (DepsMut, &Env, MessageInfo),
+    amount: Coin,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    check_is_contract_owner(deps.as_ref(), info.sender)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
 
-    let _ = validate_account(&config.receiver_prefix, receiver)?;
+    if amount.amount.is_zero() {
+        return Err(PaymentError::NoFunds {}.into());
+    }
 
-    let ack_fee_amount = Uint128::from(1000u128);
-    let timeout_fee_amount = Uint128::from(1000u128);
+    debit_fee_reserve(deps.storage, &amount)?;
 
-    let final_amount =
-        Uint128::checked_sub(amount, ack_fee_amount.checked_add(timeout_fee_amount)?)?;
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![amount.clone()],
+        })
+        .add_attribute("action", "withdraw_fee_reserve")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount.to_string()))
+}
 
-    let coin = StdCoin {
-        denom: denom.to_string(),
-        amount: final_amount.to_string(),
-    };
+// Takes every coin escrowed with a packet out of the reserve
+fn charge_fee_reserve(storage: &mut dyn Storage, fee: &IbcFee) -> Result<(), ContractError> {
+    for coin in fee.coins() {
+        debit_fee_reserve(storage, coin)?;
+    }
+    Ok(())
+}
 
-    let fee = Fee {
-        recv_fee: vec![],
-        ack_fee: vec![create_coin(&denom, ack_fee_amount)],
-        timeout_fee: vec![create_coin(&denom, timeout_fee_amount)],
-    };
+fn debit_fee_reserve(storage: &mut dyn Storage, coin: &Coin) -> Result<(), ContractError> {
+    FEE_RESERVE.update(storage, &coin.denom, |reserve| {
+        reserve
+            .unwrap_or_default()
+            .checked_sub(coin.amount.u128())
+            .ok_or(ContractError::InsufficientFeeReserve {
+                denom: coin.denom.clone(),
+            })
+    })?;
+    transfer::release_liability(storage, &coin.denom, coin.amount.u128())
+}
 
-    let msg = MsgTransfer {
-        source_port: "transfer".to_string(),
-        source_channel: config.channel_id,
-        token: Some(coin),
-        receiver: receiver.to_string(),
-        timeout_height: Some(Height {
-            revision_height: 2,
-            revision_number: DEFAULT_TIMEOUT_HEIGHT,
-        }),
-        timeout_timestamp: env.block.time.plus_seconds(600).nanos(),
-        fee: Some(fee),
-        memo: "".to_string(),
-        sender: env.contract.address.to_string(),
-    };
+/// Returns fee coins to the reserve, e.g. the part of a packet fee the feerefunder
+/// refunded because the relayer did not earn it
+pub fn credit_fee_reserve(storage: &mut dyn Storage, coins: &[Coin]) -> Result<(), ContractError> {
+    for coin in coins {
+        FEE_RESERVE.update(storage, &coin.denom, |reserve| {
+            reserve
+                .unwrap_or_default()
+                .checked_add(coin.amount.u128())
+                .ok_or(ContractError::ArithmeticError {})
+        })?;
+        transfer::record_liability(storage, &coin.denom, coin.amount.u128())?;
+    }
+    Ok(())
+}
 
-    Ok(msg)
+pub fn get_fee_reserve(deps: Deps) -> StdResult<Vec<Coin>> {
+    FEE_RESERVE
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin::new(amount, denom)))
+        .collect()
+}
+
+pub fn get_ibc_fees(deps: Deps) -> StdResult<Vec<DenomFee>> {
+    IBC_FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(denom, fee)| DenomFee {
+                denom,
+                ack_fee: fee.ack_fee,
+                timeout_fee: fee.timeout_fee,
+            })
+        })
+        .collect()
 }
 
 pub fn create_coin(denom: &str, amount: Uint128) -> StdCoin {
@@ -177,7 +288,3 @@ pub fn create_coin(denom: &str, amount: Uint128) -> StdCoin {
         amount: amount.to_string(),
     }
 }
-
-pub fn ibc_message_event(context: &str) -> cosmwasm_std::Event {
-    cosmwasm_std::Event::new("ibc_message_added").add_attribute("context", context)
-}
@@ -1,11 +1,19 @@
-use crate::msg::{SudoPayload, Type1, Type2};
-use crate::state::{read_reply_payload, read_sudo_payload, save_reply_payload, save_sudo_payload};
+use crate::helpers::credit_fee_reserve;
+use crate::msg::{PendingPayloadsResponse, SudoPayload};
+use crate::state::{
+    pending_payload_counts, save_reply_payload, save_sudo_payload, take_reply_payload,
+    take_sudo_payload, IbcFee, RECOVERY_STATES,
+};
 use neutron_sdk::interchain_txs::helpers::decode_message_response;
 use neutron_sdk::sudo::msg::RequestPacket;
 use neutron_std::types::neutron::transfer::MsgTransferResponse;
+use quizzler_core::execute::revert_reward;
+use quizzler_core::transfer::{self, Transfer, TransferKind};
+use quizzler_core::ContractError;
 
 use cosmwasm_std::{
-    Binary, CosmosMsg, Deps, DepsMut, Env, Reply, Response, StdError, StdResult, SubMsg,
+    Addr, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, Reply, Response, StdError, StdResult,
+    Storage, SubMsg,
 };
 
 pub fn msg_with_sudo_callback<C: Into<CosmosMsg<T>>, T>(
@@ -17,7 +25,31 @@ pub fn msg_with_sudo_callback<C: Into<CosmosMsg<T>>, T>(
     Ok(SubMsg::reply_on_success(msg, id))
 }
 
-pub fn sudo_error(deps: DepsMut, req: RequestPacket, data: String) -> StdResult<Response> {
+/// How Neutron resolved an outgoing transfer packet
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PacketOutcome {
+    Acknowledged,
+    Error,
+    Timeout,
+}
+
+impl PacketOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PacketOutcome::Acknowledged => "ack",
+            PacketOutcome::Error => "ack_error",
+            PacketOutcome::Timeout => "timeout",
+        }
+    }
+}
+
+/// Also reached when a hop of a forwarded transfer fails, as packet-forward-middleware
+/// answers the first packet with an error ack once the funds are refunded.
+pub fn sudo_error(
+    deps: DepsMut,
+    req: RequestPacket,
+    data: String,
+) -> Result<Response, ContractError> {
     deps.api.debug(
         format!(
             "WASMDEBUG: sudo_error: sudo error received: {:?} {}",
@@ -25,10 +57,10 @@ pub fn sudo_error(deps: DepsMut, req: RequestPacket, data: String) -> StdResult<
         )
         .as_str(),
     );
-    Ok(Response::new())
+    resolve_packet(deps, req, PacketOutcome::Error)
 }
 
-pub fn sudo_timeout(deps: DepsMut, req: RequestPacket) -> StdResult<Response> {
+pub fn sudo_timeout(deps: DepsMut, req: RequestPacket) -> Result<Response, ContractError> {
     deps.api.debug(
         format!(
             "WASMDEBUG: sudo_timeout: sudo timeout ack received: {:?}",
@@ -36,10 +68,14 @@ pub fn sudo_timeout(deps: DepsMut, req: RequestPacket) -> StdResult<Response> {
         )
         .as_str(),
     );
-    Ok(Response::new())
+    resolve_packet(deps, req, PacketOutcome::Timeout)
 }
 
-pub fn sudo_response(deps: DepsMut, req: RequestPacket, data: Binary) -> StdResult<Response> {
+pub fn sudo_response(
+    deps: DepsMut,
+    req: RequestPacket,
+    data: Binary,
+) -> Result<Response, ContractError> {
     deps.api.debug(
         format!(
             "WASMDEBUG: sudo_response: sudo received: {:?} {}",
@@ -47,22 +83,170 @@ pub fn sudo_response(deps: DepsMut, req: RequestPacket, data: Binary) -> StdResu
         )
         .as_str(),
     );
-    let seq_id = req
-        .sequence
-        .ok_or_else(|| StdError::generic_err("sequence not found"))?;
+    resolve_packet(deps, req, PacketOutcome::Acknowledged)
+}
+
+// Removes the payload stored for the packet and hands it to the handler of its variant
+fn resolve_packet(
+    deps: DepsMut,
+    req: RequestPacket,
+    outcome: PacketOutcome,
+) -> Result<Response, ContractError> {
+    let seq_id = req.sequence.ok_or(ContractError::SequenceNotFound {})?;
     let channel_id = req
         .source_channel
-        .ok_or_else(|| StdError::generic_err("channel_id not found"))?;
-    match read_sudo_payload(deps.storage, channel_id, seq_id)? {
-        SudoPayload::HandlerPayload1(t1) => sudo_callback1(deps.as_ref(), t1),
-        SudoPayload::HandlerPayload2(t2) => sudo_callback2(deps.as_ref(), t2),
+        .ok_or(ContractError::ChannelIdNotFound {})?;
+
+    let payload = take_sudo_payload(deps.storage, channel_id, seq_id).map_err(|err| {
+        ContractError::PayloadDeserializationFailed {
+            error: err.to_string(),
+        }
+    })?;
+
+    match payload {
+        Some(SudoPayload::RewardPayout {
+            survey_id,
+            participant,
+            coin,
+            fee,
+        }) => {
+            refund_unused_fee(deps.storage, outcome, fee)?;
+            reward_payout_callback(deps, outcome, survey_id, participant, coin)
+        }
+        Some(SudoPayload::CreatorRefund {
+            survey_id,
+            creator,
+            coin,
+            fee,
+        }) => {
+            refund_unused_fee(deps.storage, outcome, fee)?;
+            creator_refund_callback(deps, outcome, survey_id, creator, coin)
+        }
+        None => Ok(Response::new()
+            .add_attribute("action", "sudo_callback")
+            .add_attribute("outcome", outcome.as_str())
+            .add_attribute("msg", "received unexpected callback")),
     }
-    // at this place we can safely remove the data under (channel_id, seq_id) key
-    // but it costs an extra gas, so its on you how to use the storage
 }
+
+// The relayer earns the ack fee on (error) acks and the timeout fee on timeouts, the
+// feerefunder sends the other part back to the contract
+fn refund_unused_fee(
+    storage: &mut dyn Storage,
+    outcome: PacketOutcome,
+    fee: Option<IbcFee>,
+) -> Result<(), ContractError> {
+    let Some(fee) = fee else {
+        return Ok(());
+    };
+
+    let refunded = match outcome {
+        PacketOutcome::Acknowledged | PacketOutcome::Error => fee.timeout_fee,
+        PacketOutcome::Timeout => fee.ack_fee,
+    };
+
+    credit_fee_reserve(storage, &refunded)
+}
+
+/// A failed reward on a survey that is still open is rolled back, so the participant can
+/// be paid again by the next `PayRewards`. If the survey has been cancelled in the
+/// meantime the reward is parked in RECOVERY_STATES for `ExecuteMsg::Recover {}`.
+fn reward_payout_callback(
+    deps: DepsMut,
+    outcome: PacketOutcome,
+    survey_id: String,
+    participant: Addr,
+    coin: Coin,
+) -> Result<Response, ContractError> {
+    let response = Response::new()
+        .add_attribute("action", "reward_payout_callback")
+        .add_attribute("outcome", outcome.as_str())
+        .add_attribute("survey_id", survey_id.as_str())
+        .add_attribute("participant", participant.as_str())
+        .add_attribute("amount", coin.to_string());
+
+    if outcome == PacketOutcome::Acknowledged {
+        return Ok(response.add_attribute("msg", "reward delivered"));
+    }
+
+    if revert_reward(deps.storage, &survey_id, &participant)? {
+        return Ok(response.add_attribute("msg", "reward reverted"));
+    }
+
+    store_recovery(
+        deps,
+        Transfer {
+            kind: TransferKind::Reward,
+            survey_id,
+            recipient: participant,
+            coin,
+        },
+    )?;
+
+    Ok(response.add_attribute("msg", "recovery stored"))
+}
+
+/// The survey stays cancelled, so a failed refund is parked in RECOVERY_STATES for
+/// `ExecuteMsg::Recover {}`.
+fn creator_refund_callback(
+    deps: DepsMut,
+    outcome: PacketOutcome,
+    survey_id: String,
+    creator: Addr,
+    coin: Coin,
+) -> Result<Response, ContractError> {
+    let response = Response::new()
+        .add_attribute("action", "creator_refund_callback")
+        .add_attribute("outcome", outcome.as_str())
+        .add_attribute("survey_id", survey_id.as_str())
+        .add_attribute("creator", creator.as_str())
+        .add_attribute("amount", coin.to_string());
+
+    if outcome == PacketOutcome::Acknowledged {
+        return Ok(response.add_attribute("msg", "refund delivered"));
+    }
+
+    store_recovery(
+        deps,
+        Transfer {
+            kind: TransferKind::Refund,
+            survey_id,
+            recipient: creator,
+            coin,
+        },
+    )?;
+
+    Ok(response.add_attribute("msg", "recovery stored"))
+}
+
+fn store_recovery(deps: DepsMut, transfer: Transfer) -> Result<(), ContractError> {
+    let recovery_addr = transfer.recipient.clone();
+
+    // The funds are back in the contract and owed to the recovery address
+    transfer::record_liability(
+        deps.storage,
+        &transfer.coin.denom,
+        transfer.coin.amount.u128(),
+    )?;
+    RECOVERY_STATES.update(deps.storage, &recovery_addr, |recoveries| {
+        let mut recoveries = recoveries.unwrap_or_default();
+        recoveries.push(transfer);
+        Ok::<_, ContractError>(recoveries)
+    })?;
+    Ok(())
+}
+
+pub fn get_pending_payloads(deps: Deps) -> StdResult<PendingPayloadsResponse> {
+    let (pending_replies, pending_sudo_payloads) = pending_payload_counts(deps.storage)?;
+    Ok(PendingPayloadsResponse {
+        pending_replies,
+        pending_sudo_payloads,
+    })
+}
+
 // Process the reply and save the packet information
 pub fn prepare_sudo_payload(mut deps: DepsMut, _env: Env, msg: Reply) -> StdResult<Response> {
-    let payload = read_reply_payload(deps.storage, msg.id)?;
+    let payload = take_reply_payload(deps.storage, msg.id)?;
     let resp: MsgTransferResponse = decode_message_response(
         &msg.result
             .into_result()
@@ -82,15 +266,3 @@ pub fn prepare_sudo_payload(mut deps: DepsMut, _env: Env, msg: Reply) -> StdResu
         .add_attribute("sequence_id", seq_id.to_string())
         .add_attribute("channel", channel_id.to_string()))
 }
-
-fn sudo_callback1(deps: Deps, payload: Type1) -> StdResult<Response> {
-    deps.api
-        .debug(format!("WASMDEBUG: callback1: sudo payload: {:?}", payload).as_str());
-    Ok(Response::new())
-}
-
-fn sudo_callback2(deps: Deps, payload: Type2) -> StdResult<Response> {
-    deps.api
-        .debug(format!("WASMDEBUG: callback2: sudo payload: {:?}", payload).as_str());
-    Ok(Response::new())
-}
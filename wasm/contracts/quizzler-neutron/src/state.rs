@@ -1,32 +1,14 @@
-use cosmwasm_std::{from_json, to_json_vec, Addr, Binary, StdResult, Storage};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_json, to_json_vec, Addr, Binary, Coin, Order, StdError, StdResult, Storage,
+};
 use cw_storage_plus::{Item, Map};
 use neutron_std::types::neutron::feerefunder::Fee;
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use quizzler_core::transfer::Transfer;
 
+use crate::helpers::create_coin;
 use crate::msg::SudoPayload;
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct ManagerInfo {
-    pub address: Addr,
-    pub pub_key: Binary,
-    pub status: bool,
-}
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct Config {
-    pub owner: Addr,
-    pub receiver_prefix: String,
-    pub channel_id: String,
-}
-
-pub const CONFIG: Item<Config> = Item::new("config");
-
-// Managers
-pub const MANAGERS: Map<&Addr, ManagerInfo> = Map::new("managers");
-
 // Store IBC packet information
 pub const SUDO_PAYLOAD: Map<(String, u64), Vec<u8>> = Map::new("sudo_payload");
 
@@ -36,64 +18,58 @@ pub const IBC_SUDO_ID_RANGE_SIZE: u64 = 1_000;
 
 pub const IBC_SUDO_ID_RANGE_END: u64 = IBC_SUDO_ID_RANGE_START + IBC_SUDO_ID_RANGE_SIZE;
 
-pub const IBC_FEE: Item<Option<Fee>> = Item::new("ibc_fee");
-
-pub const REPLY_QUEUE_ID: Map<u64, Vec<u8>> = Map::new("reply_queue_id");
-
-const REPLY_ID: Item<u64> = Item::new("reply_id");
+/// Relayer fee escrowed with every outgoing transfer, by payout denom
+pub const IBC_FEES: Map<&str, IbcFee> = Map::new("ibc_fees");
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct SurveyInfo {
-    pub survey_creator: Addr,
-    pub participants_limit: u32,
-    pub reward_amount: u128,
-    pub participants_rewarded: u32,
-    pub survey_hash: String,
-    pub is_cancelled: bool,
-    pub reward_denom: String,
+/// The ack and timeout parts of a feerefunder `Fee`, the recv fee is always empty
+#[cw_serde]
+pub struct IbcFee {
+    pub ack_fee: Vec<Coin>,
+    pub timeout_fee: Vec<Coin>,
 }
 
-pub const SURVEYS: Map<&str, SurveyInfo> = Map::new("surveys");
-
-// Survey rewarded users
-pub const SURVEY_REWARDED_USERS: Map<(&str, &Addr), bool> = Map::new("survey_rewarded_users");
-
-// Used proof tokens
-pub const USED_PROOF_TOKENS: Map<&String, bool> = Map::new("used_proof_tokens");
-
-#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct CreateSurveyPayload<'a> {
-    pub token: &'a str,
-    pub time_to_expire: u64,
-    pub owner: &'a str,
-    pub survey_id: &'a str,
-    pub participants_limit: u32,
-    pub reward_amount: u128,
-    pub survey_hash: &'a str,
-    pub reward_denom: &'a str,
-    pub domain: &'a str,
+impl IbcFee {
+    /// Every coin escrowed with a packet, only one of the two parts is paid to the relayer
+    pub fn coins(&self) -> impl Iterator<Item = &Coin> {
+        self.ack_fee.iter().chain(self.timeout_fee.iter())
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct CancelSurveyPayload<'a> {
-    pub token: &'a str,
-    pub time_to_expire: u64,
-    pub survey_id: &'a str,
-    pub domain: &'a str,
+/// Funds set aside for relayer fees, by denom. They are part of the liabilities so they
+/// can't be swept as surplus.
+pub const FEE_RESERVE: Map<&str, u128> = Map::new("fee_reserve");
+
+impl From<IbcFee> for Fee {
+    fn from(fee: IbcFee) -> Self {
+        Fee {
+            recv_fee: vec![],
+            ack_fee: fee
+                .ack_fee
+                .iter()
+                .map(|coin| create_coin(&coin.denom, coin.amount))
+                .collect(),
+            timeout_fee: fee
+                .timeout_fee
+                .iter()
+                .map(|coin| create_coin(&coin.denom, coin.amount))
+                .collect(),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct PayRewardsPayload<'a> {
-    pub token: &'a str,
-    pub time_to_expire: u64,
-    pub survey_id: Vec<String>,
-    pub participants: Vec<String>,
-    pub domain: &'a str,
-}
+pub const REPLY_QUEUE_ID: Map<u64, Vec<u8>> = Map::new("reply_queue_id");
+
+const REPLY_ID: Item<u64> = Item::new("reply_id");
+
+/// Number of payloads in REPLY_QUEUE_ID, kept up to date by the save/take functions
+const PENDING_REPLIES: Item<u64> = Item::new("pending_replies");
+
+/// Number of payloads in SUDO_PAYLOAD, kept up to date by the save/take functions
+const PENDING_SUDO_PAYLOADS: Item<u64> = Item::new("pending_sudo_payloads");
+
+/// Payouts that failed and could not be rolled back, by recipient. They can be re-sent
+/// with `ExecuteMsg::Recover {}`.
+pub const RECOVERY_STATES: Map<&Addr, Vec<Transfer>> = Map::new("recovery");
 
 pub fn save_sudo_payload(
     store: &mut dyn Storage,
@@ -101,34 +77,108 @@ pub fn save_sudo_payload(
     seq_id: u64,
     payload: SudoPayload,
 ) -> StdResult<()> {
-    SUDO_PAYLOAD.save(store, (channel_id, seq_id), &to_json_vec(&payload)?)
+    let key = (channel_id, seq_id);
+    if !SUDO_PAYLOAD.has(store, key.clone()) {
+        adjust_count(store, &PENDING_SUDO_PAYLOADS, 1)?;
+    }
+    SUDO_PAYLOAD.save(store, key, &to_json_vec(&payload)?)
 }
 
-pub fn read_sudo_payload(
-    store: &dyn Storage,
+/// Loads and removes the payload of a packet once Neutron reported its outcome.
+/// Returns `None` for packets this contract is not tracking.
+pub fn take_sudo_payload(
+    store: &mut dyn Storage,
     channel_id: String,
     seq_id: u64,
-) -> StdResult<SudoPayload> {
-    let data = SUDO_PAYLOAD.load(store, (channel_id, seq_id))?;
-    from_json(Binary::new(data))
+) -> StdResult<Option<SudoPayload>> {
+    let key = (channel_id, seq_id);
+    let Some(data) = SUDO_PAYLOAD.may_load(store, key.clone())? else {
+        return Ok(None);
+    };
+    SUDO_PAYLOAD.remove(store, key);
+    adjust_count(store, &PENDING_SUDO_PAYLOADS, -1)?;
+    from_json(Binary::new(data)).map(Some)
 }
 
+/// Allocates the next reply id in the IBC sudo range, wrapping around at the end of the
+/// range but never handing out an id whose payload is still waiting for its reply.
 pub fn get_next_id(store: &mut dyn Storage) -> StdResult<u64> {
     let mut id = REPLY_ID.may_load(store)?.unwrap_or(IBC_SUDO_ID_RANGE_START);
-    if id > IBC_SUDO_ID_RANGE_END {
-        id = IBC_SUDO_ID_RANGE_START
+
+    // The range is inclusive on both ends
+    for _ in 0..=IBC_SUDO_ID_RANGE_SIZE {
+        if id > IBC_SUDO_ID_RANGE_END {
+            id = IBC_SUDO_ID_RANGE_START
+        }
+        if !REPLY_QUEUE_ID.has(store, id) {
+            REPLY_ID.save(store, &(id + 1))?;
+            return Ok(id);
+        }
+        id += 1;
     }
-    REPLY_ID.save(store, &(id + 1))?;
-    Ok(id)
+
+    Err(StdError::generic_err(
+        "no free reply id: every id in the IBC sudo range has a pending payload",
+    ))
 }
 
 pub fn save_reply_payload(store: &mut dyn Storage, payload: SudoPayload) -> StdResult<u64> {
     let id = get_next_id(store)?;
     REPLY_QUEUE_ID.save(store, id, &to_json_vec(&payload)?)?;
+    adjust_count(store, &PENDING_REPLIES, 1)?;
     Ok(id)
 }
 
-pub fn read_reply_payload(store: &dyn Storage, id: u64) -> StdResult<SudoPayload> {
+/// Loads and removes the payload queued for a submessage reply.
+pub fn take_reply_payload(store: &mut dyn Storage, id: u64) -> StdResult<SudoPayload> {
     let data = REPLY_QUEUE_ID.load(store, id)?;
+    REPLY_QUEUE_ID.remove(store, id);
+    adjust_count(store, &PENDING_REPLIES, -1)?;
     from_json(Binary::new(data))
 }
+
+/// Brings the payload maps and their counters in line after a code migration, returning
+/// the number of (reply queue, sudo) payloads removed.
+///
+/// No submessage reply is pending across a migration, so every queued reply payload is
+/// stale and removed. Sudo payloads stored in an earlier format can never be read back
+/// by `take_sudo_payload` and are dropped too. Both counters are then set from what is
+/// left.
+pub fn reset_pending_payloads(store: &mut dyn Storage) -> StdResult<(u64, u64)> {
+    let reply_ids = REPLY_QUEUE_ID
+        .keys(store, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for id in reply_ids.iter() {
+        REPLY_QUEUE_ID.remove(store, *id);
+    }
+    PENDING_REPLIES.save(store, &0)?;
+
+    let mut pending_sudo_payloads = 0;
+    let mut stale_sudo_payloads = vec![];
+    for item in SUDO_PAYLOAD.range(store, None, None, Order::Ascending) {
+        let (key, data) = item?;
+        if from_json::<SudoPayload>(Binary::new(data)).is_ok() {
+            pending_sudo_payloads += 1;
+        } else {
+            stale_sudo_payloads.push(key);
+        }
+    }
+    for key in stale_sudo_payloads.iter() {
+        SUDO_PAYLOAD.remove(store, key.clone());
+    }
+    PENDING_SUDO_PAYLOADS.save(store, &pending_sudo_payloads)?;
+
+    Ok((reply_ids.len() as u64, stale_sudo_payloads.len() as u64))
+}
+
+/// Number of (reply queue, sudo) payloads currently stored
+pub fn pending_payload_counts(store: &dyn Storage) -> StdResult<(u64, u64)> {
+    let pending_replies = PENDING_REPLIES.may_load(store)?.unwrap_or_default();
+    let pending_sudo_payloads = PENDING_SUDO_PAYLOADS.may_load(store)?.unwrap_or_default();
+    Ok((pending_replies, pending_sudo_payloads))
+}
+
+fn adjust_count(store: &mut dyn Storage, counter: &Item<u64>, delta: i64) -> StdResult<()> {
+    let count = counter.may_load(store)?.unwrap_or_default();
+    counter.save(store, &count.saturating_add_signed(delta))
+}
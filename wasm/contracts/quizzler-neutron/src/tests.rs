@@ -0,0 +1,743 @@
+use crate::contract::{execute, instantiate, migrate, query, reply, sudo};
+use crate::msg::{
+    DenomFee, ExecuteMsg, InstantiateMsg, Manager, ManagerSignature, MigrateMsg,
+    PendingPayloadsResponse, QueryMsg, Route, SolvencyResponse,
+};
+use crate::state::{IBC_SUDO_ID_RANGE_END, IBC_SUDO_ID_RANGE_START};
+use cosmwasm_std::testing::{MockApi, MockStorage};
+use cosmwasm_std::{
+    coins, Addr, AnyMsg, Api, BankMsg, Binary, BlockInfo, Coin, CustomMsg, CustomQuery, Empty,
+    Event, MsgResponse, Storage, Uint128,
+};
+use cw_multi_test::error::{bail, AnyResult};
+use cw_multi_test::{
+    App, AppBuilder, AppResponse, BankKeeper, BankSudo, ContractWrapper, CosmosRouter,
+    DistributionKeeper, Executor, FailingModule, GovFailingModule, IbcFailingModule, IntoBech32,
+    StakeKeeper, Stargate, WasmKeeper,
+};
+use cw_storage_plus::{Item, Map};
+use ed25519_dalek::{Signer, SigningKey};
+use neutron_sdk::sudo::msg::{RequestPacket, TransferSudoMsg};
+use neutron_std::types::neutron::transfer::{MsgTransfer, MsgTransferResponse};
+use prost::Message;
+use quizzler_core::msg::ForwardHop;
+use quizzler_core::state::KeyType;
+use quizzler_core::transfer::{Transfer, TransferKind};
+use quizzler_core::ContractError;
+use serde::de::DeserializeOwned;
+
+const DENOM: &str = "untrn";
+const CHANNEL: &str = "channel-0";
+const REWARD: u128 = 100;
+
+type TestApp = App<
+    BankKeeper,
+    MockApi,
+    MockStorage,
+    FailingModule<Empty, Empty, Empty>,
+    WasmKeeper<Empty, Empty>,
+    StakeKeeper,
+    DistributionKeeper,
+    IbcFailingModule,
+    GovFailingModule,
+    NeutronTransferModule,
+>;
+
+/// Stands in for Neutron's `transfer` module. The sent funds and the relayer fee are
+/// burned as if escrowed, and the reply carries the packet sequence and channel like a
+/// real `MsgTransfer`.
+struct NeutronTransferModule;
+
+const NEXT_SEQUENCE: Item<u64> = Item::new("next_sequence");
+
+impl Stargate for NeutronTransferModule {
+    fn execute_any<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: AnyMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        if msg.type_url != "/neutron.transfer.MsgTransfer" {
+            bail!("unsupported message {}", msg.type_url);
+        }
+        let transfer = MsgTransfer::decode(msg.value.as_slice())?;
+
+        let fee = transfer.fee.clone().unwrap_or_default();
+        let escrowed = transfer
+            .token
+            .iter()
+            .chain(fee.recv_fee.iter())
+            .chain(fee.ack_fee.iter())
+            .chain(fee.timeout_fee.iter())
+            .map(|coin| Ok(Coin::new(coin.amount.parse::<Uint128>()?, &coin.denom)))
+            .collect::<AnyResult<Vec<_>>>()?;
+        router.execute(
+            api,
+            storage,
+            block,
+            sender,
+            BankMsg::Burn { amount: escrowed }.into(),
+        )?;
+
+        let sequence = NEXT_SEQUENCE.may_load(storage)?.unwrap_or(1);
+        NEXT_SEQUENCE.save(storage, &(sequence + 1))?;
+
+        Ok(AppResponse {
+            events: vec![Event::new("transfer")
+                .add_attribute("sequence", sequence.to_string())
+                .add_attribute("source_channel", &transfer.source_channel)
+                .add_attribute("receiver", &transfer.receiver)
+                .add_attribute("memo", &transfer.memo)],
+            msg_responses: vec![MsgResponse {
+                type_url: "/neutron.transfer.MsgTransferResponse".to_string(),
+                value: MsgTransferResponse {
+                    sequence_id: sequence,
+                    channel: transfer.source_channel,
+                }
+                .encode_to_vec()
+                .into(),
+            }],
+            ..Default::default()
+        })
+    }
+}
+
+/// How the test resolves a packet, mirroring the `TransferSudoMsg` variants
+#[derive(Clone, Copy)]
+enum Outcome {
+    Ack,
+    Error,
+    Timeout,
+}
+
+struct Suite {
+    app: TestApp,
+    code_id: u64,
+    contract: Addr,
+    owner: Addr,
+    funder: Addr,
+    keys: Vec<SigningKey>,
+}
+
+fn setup() -> Suite {
+    let api = MockApi::default();
+    let owner = api.addr_make("owner");
+    let funder = api.addr_make("funder");
+
+    let mut app = AppBuilder::new()
+        .with_stargate(NeutronTransferModule)
+        .build(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &funder, coins(10_000, DENOM))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &owner, coins(10_000, DENOM))
+                .unwrap();
+        });
+
+    let code_id = app.store_code(Box::new(
+        ContractWrapper::new(execute, instantiate, query)
+            .with_sudo(sudo)
+            .with_reply(reply)
+            .with_migrate(migrate),
+    ));
+
+    let keys = vec![SigningKey::from_bytes(&[1; 32])];
+    let managers = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| Manager {
+            addr: api.addr_make(&format!("manager{index}")).to_string(),
+            pub_key: Binary::from(key.verifying_key().to_bytes()).to_base64(),
+            key_type: KeyType::Ed25519,
+        })
+        .collect();
+
+    let contract = app
+        .instantiate_contract(
+            code_id,
+            owner.clone(),
+            &InstantiateMsg {
+                managers,
+                routes: vec![Route {
+                    prefix: "agoric".to_string(),
+                    channel_id: CHANNEL.to_string(),
+                    forward: vec![],
+                }],
+                ibc_timeout_seconds: None,
+                ibc_timeout_block: None,
+                ibc_fees: None,
+            },
+            &[],
+            "quizzler",
+            Some(owner.to_string()),
+        )
+        .unwrap();
+
+    Suite {
+        app,
+        code_id,
+        contract,
+        owner,
+        funder,
+        keys,
+    }
+}
+
+impl Suite {
+    fn now(&self) -> u64 {
+        self.app.block_info().time.seconds()
+    }
+
+    fn query<T: DeserializeOwned>(&self, msg: &QueryMsg) -> T {
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.contract, msg)
+            .unwrap()
+    }
+
+    fn sign(&self, proof: &Binary) -> Vec<ManagerSignature> {
+        let key = &self.keys[0];
+        vec![ManagerSignature {
+            pub_key: Binary::from(key.verifying_key().to_bytes()).to_base64(),
+            signature: Binary::from(key.sign(proof.as_slice()).to_bytes()).to_base64(),
+        }]
+    }
+
+    fn create_survey(&mut self, survey_id: &str, owner: &str, participants_limit: u32) {
+        let token = format!("create-{survey_id}");
+        let time_to_expire = self.now() + 600;
+        let proof: Binary = self.query(&QueryMsg::CreateSurveyProof {
+            token: token.clone(),
+            time_to_expire,
+            owner: owner.to_string(),
+            survey_id: survey_id.to_string(),
+            participants_limit,
+            reward_amount: REWARD,
+            survey_hash: "hash".to_string(),
+            reward_denom: DENOM.to_string(),
+            ends_at: None,
+            domain_version: None,
+        });
+
+        self.app
+            .execute_contract(
+                self.funder.clone(),
+                self.contract.clone(),
+                &ExecuteMsg::CreateSurvey {
+                    signatures: self.sign(&proof),
+                    token,
+                    time_to_expire,
+                    owner: owner.to_string(),
+                    survey_id: survey_id.to_string(),
+                    participants_limit,
+                    reward_denom: DENOM.to_string(),
+                    reward_amount: REWARD,
+                    survey_hash: "hash".to_string(),
+                    ends_at: None,
+                    domain_version: None,
+                },
+                &coins(participants_limit as u128 * REWARD, DENOM),
+            )
+            .unwrap();
+    }
+
+    fn pay_rewards(
+        &mut self,
+        survey_id: &str,
+        participants: &[&str],
+        token: &str,
+    ) -> AnyResult<AppResponse> {
+        let survey_ids = vec![survey_id.to_string(); participants.len()];
+        let participants: Vec<String> = participants.iter().map(|p| p.to_string()).collect();
+        let time_to_expire = self.now() + 600;
+        let proof: Binary = self.query(&QueryMsg::PayRewardsProof {
+            token: token.to_string(),
+            time_to_expire,
+            survey_ids: survey_ids.clone(),
+            participants: participants.clone(),
+            domain_version: None,
+        });
+
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::PayRewards {
+                signatures: self.sign(&proof),
+                token: token.to_string(),
+                time_to_expire,
+                survey_ids,
+                participants,
+                domain_version: None,
+            },
+            &[],
+        )
+    }
+
+    fn cancel_survey(&mut self, survey_id: &str) {
+        let token = format!("cancel-{survey_id}");
+        let time_to_expire = self.now() + 600;
+        let proof: Binary = self.query(&QueryMsg::CancelSurveyProof {
+            token: token.clone(),
+            time_to_expire,
+            survey_id: survey_id.to_string(),
+            domain_version: None,
+        });
+
+        self.app
+            .execute_contract(
+                self.owner.clone(),
+                self.contract.clone(),
+                &ExecuteMsg::CancelSurvey {
+                    signatures: self.sign(&proof),
+                    token,
+                    time_to_expire,
+                    survey_id: survey_id.to_string(),
+                    domain_version: None,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    /// Reports the outcome of packet `sequence` the way Neutron does. On an error or a
+    /// timeout the transfer module first refunds the escrowed `amount`.
+    fn resolve(&mut self, sequence: u64, amount: u128, outcome: Outcome) -> AppResponse {
+        if !matches!(outcome, Outcome::Ack) {
+            self.mint(amount);
+        }
+
+        let request = RequestPacket {
+            sequence: Some(sequence),
+            source_port: Some("transfer".to_string()),
+            source_channel: Some(CHANNEL.to_string()),
+            destination_port: None,
+            destination_channel: None,
+            data: None,
+            timeout_height: None,
+            timeout_timestamp: None,
+        };
+        let msg = match outcome {
+            Outcome::Ack => TransferSudoMsg::Response {
+                request,
+                data: Binary::default(),
+            },
+            Outcome::Error => TransferSudoMsg::Error {
+                request,
+                details: "failed".to_string(),
+            },
+            Outcome::Timeout => TransferSudoMsg::Timeout { request },
+        };
+        self.app.wasm_sudo(self.contract.clone(), &msg).unwrap()
+    }
+
+    fn mint(&mut self, amount: u128) {
+        self.app
+            .sudo(
+                BankSudo::Mint {
+                    to_address: self.contract.to_string(),
+                    amount: coins(amount, DENOM),
+                }
+                .into(),
+            )
+            .unwrap();
+    }
+
+    fn migrate(&mut self) -> AppResponse {
+        self.app
+            .migrate_contract(
+                self.owner.clone(),
+                self.contract.clone(),
+                &MigrateMsg {},
+                self.code_id,
+            )
+            .unwrap()
+    }
+
+    fn fee_reserve(&self) -> Vec<Coin> {
+        self.query(&QueryMsg::GetFeeReserve {})
+    }
+
+    fn withdraw_fee_reserve(&mut self, sender: &Addr, amount: u128) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::WithdrawFeeReserve {
+                amount: Coin::new(amount, DENOM),
+                recipient: self.owner.to_string(),
+            },
+            &[],
+        )
+    }
+
+    fn recoveries(&self, addr: &str) -> Vec<Transfer> {
+        self.query(&QueryMsg::GetRecoveries {
+            addr: addr.to_string(),
+        })
+    }
+
+    fn has_claimed(&self, survey_id: &str, participant: &str) -> bool {
+        self.query(&QueryMsg::GetHasClaimedReward {
+            survey_id: survey_id.to_string(),
+            participant: participant.to_string(),
+        })
+    }
+
+    fn pending_payloads(&self) -> PendingPayloadsResponse {
+        self.query(&QueryMsg::GetPendingPayloads {})
+    }
+
+    /// Checks the contract holds what it owes, returns its (balance, liabilities)
+    fn assert_solvent(&self) -> (u128, u128) {
+        let solvency: SolvencyResponse = self.query(&QueryMsg::GetSolvency {
+            denom: DENOM.to_string(),
+        });
+        assert!(
+            solvency.balance >= solvency.liabilities,
+            "balance {} below liabilities {}",
+            solvency.balance,
+            solvency.liabilities
+        );
+        (solvency.balance, solvency.liabilities)
+    }
+}
+
+fn attribute(response: &AppResponse, key: &str) -> Option<String> {
+    response
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attribute| attribute.key == key)
+        .map(|attribute| attribute.value.clone())
+}
+
+#[test]
+fn acknowledged_reward_is_delivered() {
+    let mut suite = setup();
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+
+    suite.create_survey("s1", &creator, 2);
+    suite.pay_rewards("s1", &[&alice], "pay").unwrap();
+    assert_eq!(
+        suite.pending_payloads(),
+        PendingPayloadsResponse {
+            pending_replies: 0,
+            pending_sudo_payloads: 1,
+        }
+    );
+
+    let response = suite.resolve(1, REWARD, Outcome::Ack);
+    assert_eq!(attribute(&response, "msg").unwrap(), "reward delivered");
+    assert!(suite.has_claimed("s1", &alice));
+    assert!(suite.recoveries(&alice).is_empty());
+    assert_eq!(suite.pending_payloads().pending_sudo_payloads, 0);
+    assert_eq!(suite.assert_solvent(), (100, 100));
+}
+
+#[test]
+fn failed_reward_on_open_survey_is_reverted() {
+    for outcome in [Outcome::Error, Outcome::Timeout] {
+        let mut suite = setup();
+        let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+        let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+
+        suite.create_survey("s1", &creator, 2);
+        suite.pay_rewards("s1", &[&alice], "pay").unwrap();
+        assert_eq!(suite.assert_solvent(), (100, 100));
+
+        let response = suite.resolve(1, REWARD, outcome);
+        assert_eq!(attribute(&response, "msg").unwrap(), "reward reverted");
+        assert!(!suite.has_claimed("s1", &alice));
+        assert!(suite.recoveries(&alice).is_empty());
+        assert_eq!(suite.assert_solvent(), (200, 200));
+
+        // The next payout can reward her again
+        suite.pay_rewards("s1", &[&alice], "pay again").unwrap();
+        assert!(suite.has_claimed("s1", &alice));
+        assert_eq!(suite.assert_solvent(), (100, 100));
+    }
+}
+
+#[test]
+fn failed_reward_on_cancelled_survey_is_stored_for_recovery() {
+    for outcome in [Outcome::Error, Outcome::Timeout] {
+        let mut suite = setup();
+        let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+        let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+
+        suite.create_survey("s1", &creator, 2);
+        suite.pay_rewards("s1", &[&alice], "pay").unwrap();
+
+        // The refund (packet 2) already counts alice's reward as paid
+        suite.cancel_survey("s1");
+        suite.resolve(2, REWARD, Outcome::Ack);
+        assert_eq!(suite.assert_solvent(), (0, 0));
+
+        let response = suite.resolve(1, REWARD, outcome);
+        assert_eq!(attribute(&response, "msg").unwrap(), "recovery stored");
+        assert!(suite.has_claimed("s1", &alice));
+        assert_eq!(suite.recoveries(&alice).len(), 1);
+        assert_eq!(suite.assert_solvent(), (100, 100));
+    }
+}
+
+#[test]
+fn creator_refund_outcomes() {
+    for (outcome, msg) in [
+        (Outcome::Ack, "refund delivered"),
+        (Outcome::Error, "recovery stored"),
+        (Outcome::Timeout, "recovery stored"),
+    ] {
+        let mut suite = setup();
+        let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+
+        suite.create_survey("s1", &creator, 2);
+        suite.cancel_survey("s1");
+        assert_eq!(suite.assert_solvent(), (0, 0));
+
+        let response = suite.resolve(1, 2 * REWARD, outcome);
+        assert_eq!(
+            attribute(&response, "action").unwrap(),
+            "creator_refund_callback"
+        );
+        assert_eq!(attribute(&response, "msg").unwrap(), msg);
+
+        let recoveries = suite.recoveries(&creator);
+        match outcome {
+            Outcome::Ack => {
+                assert!(recoveries.is_empty());
+                assert_eq!(suite.assert_solvent(), (0, 0));
+            }
+            Outcome::Error | Outcome::Timeout => {
+                assert_eq!(recoveries.len(), 1);
+                assert_eq!(recoveries[0].kind, TransferKind::Refund);
+                assert_eq!(recoveries[0].coin, Coin::new(2 * REWARD, DENOM));
+                assert_eq!(suite.assert_solvent(), (200, 200));
+            }
+        }
+    }
+}
+
+#[test]
+fn callback_for_unknown_packet_is_ignored() {
+    let mut suite = setup();
+
+    let response = suite.resolve(7, 0, Outcome::Ack);
+    assert_eq!(
+        attribute(&response, "msg").unwrap(),
+        "received unexpected callback"
+    );
+}
+
+#[test]
+fn fee_reserve_pays_relayer_fees() {
+    const ACK_FEE: u128 = 10;
+    const TIMEOUT_FEE: u128 = 20;
+
+    let mut suite = setup();
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+    let bob = "bob".into_bech32_with_prefix("agoric").to_string();
+    let carol = "carol".into_bech32_with_prefix("agoric").to_string();
+    let dave = "dave".into_bech32_with_prefix("agoric").to_string();
+
+    suite.create_survey("s1", &creator, 2);
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.contract.clone(),
+            &ExecuteMsg::UpdateConfig {
+                ibc_timeout_seconds: 600,
+                ibc_timeout_block: None,
+                ibc_fees: Some(vec![DenomFee {
+                    denom: DENOM.to_string(),
+                    ack_fee: coins(ACK_FEE, DENOM),
+                    timeout_fee: coins(TIMEOUT_FEE, DENOM),
+                }]),
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.contract.clone(),
+            &ExecuteMsg::FundFeeReserve {},
+            &coins(100, DENOM),
+        )
+        .unwrap();
+    assert_eq!(suite.fee_reserve(), coins(100, DENOM));
+    assert_eq!(suite.assert_solvent(), (300, 300));
+
+    // Both fees are escrowed with the packet, the reward itself is not touched
+    suite.pay_rewards("s1", &[&alice], "pay alice").unwrap();
+    assert_eq!(suite.fee_reserve(), coins(70, DENOM));
+    assert_eq!(suite.assert_solvent(), (170, 170));
+
+    // On an ack the relayer earns the ack fee and the timeout fee comes back
+    suite.mint(TIMEOUT_FEE);
+    suite.resolve(1, REWARD, Outcome::Ack);
+    assert_eq!(suite.fee_reserve(), coins(90, DENOM));
+    assert_eq!(suite.assert_solvent(), (190, 190));
+
+    // On a timeout the relayer earns the timeout fee and the ack fee comes back
+    suite.pay_rewards("s1", &[&bob], "pay bob").unwrap();
+    assert_eq!(suite.assert_solvent(), (60, 60));
+    suite.mint(ACK_FEE);
+    suite.resolve(2, REWARD, Outcome::Timeout);
+    assert_eq!(suite.fee_reserve(), coins(70, DENOM));
+    assert_eq!(suite.assert_solvent(), (170, 170));
+
+    // An error ack is paid like an ack
+    suite.pay_rewards("s1", &[&carol], "pay carol").unwrap();
+    suite.mint(TIMEOUT_FEE);
+    suite.resolve(3, REWARD, Outcome::Error);
+    assert_eq!(suite.fee_reserve(), coins(60, DENOM));
+    assert_eq!(suite.assert_solvent(), (160, 160));
+
+    // Only the owner can withdraw, and only what the reserve holds
+    let stranger = suite.app.api().addr_make("stranger");
+    let err = suite.withdraw_fee_reserve(&stranger, 10).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    ));
+    let owner = suite.owner.clone();
+    let err = suite.withdraw_fee_reserve(&owner, 61).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InsufficientFeeReserve { .. }
+    ));
+    suite.withdraw_fee_reserve(&owner, 50).unwrap();
+    assert_eq!(suite.fee_reserve(), coins(10, DENOM));
+    assert_eq!(suite.assert_solvent(), (110, 110));
+
+    // A payout is refused when the reserve can't cover its fees
+    let err = suite.pay_rewards("s1", &[&dave], "pay dave").unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InsufficientFeeReserve { .. }
+    ));
+}
+
+#[test]
+fn migrate_clears_stale_payloads_and_reply_ids_wrap() {
+    let mut suite = setup();
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+    let bob = "bob".into_bech32_with_prefix("agoric").to_string();
+    let carol = "carol".into_bech32_with_prefix("agoric").to_string();
+
+    suite.create_survey("s1", &creator, 3);
+    suite.pay_rewards("s1", &[&alice], "pay alice").unwrap();
+
+    // Payloads used to stay in the reply queue for good, so on a contract that has been
+    // in use every id of the range holds one in the old format
+    let legacy_payload = br#"{"handler_payload1":{"message":"message"}}"#.to_vec();
+    let reply_queue: Map<u64, Vec<u8>> = Map::new("reply_queue_id");
+    let sudo_payloads: Map<(String, u64), Vec<u8>> = Map::new("sudo_payload");
+    let reply_id: Item<u64> = Item::new("reply_id");
+    let mut storage = suite.app.contract_storage_mut(&suite.contract);
+    for id in IBC_SUDO_ID_RANGE_START..=IBC_SUDO_ID_RANGE_END {
+        reply_queue
+            .save(storage.as_mut(), id, &legacy_payload)
+            .unwrap();
+    }
+    sudo_payloads
+        .save(storage.as_mut(), (CHANNEL.to_string(), 99), &legacy_payload)
+        .unwrap();
+    reply_id
+        .save(storage.as_mut(), &IBC_SUDO_ID_RANGE_END)
+        .unwrap();
+    drop(storage);
+
+    let err = suite.pay_rewards("s1", &[&bob], "pay bob").unwrap_err();
+    assert!(err.root_cause().to_string().contains("no free reply id"));
+
+    let response = suite.migrate();
+    assert_eq!(attribute(&response, "stale_replies").unwrap(), "1001");
+    assert_eq!(attribute(&response, "stale_sudo_payloads").unwrap(), "1");
+    assert_eq!(
+        suite.pending_payloads(),
+        PendingPayloadsResponse {
+            pending_replies: 0,
+            pending_sudo_payloads: 1,
+        }
+    );
+
+    // The last id of the range is handed out, then allocation wraps to the first
+    let reply_id_after = |suite: &Suite| {
+        reply_id
+            .load(suite.app.contract_storage(&suite.contract).as_ref())
+            .unwrap()
+    };
+    suite.pay_rewards("s1", &[&bob], "pay bob").unwrap();
+    assert_eq!(reply_id_after(&suite), IBC_SUDO_ID_RANGE_END + 1);
+    suite.pay_rewards("s1", &[&carol], "pay carol").unwrap();
+    assert_eq!(reply_id_after(&suite), IBC_SUDO_ID_RANGE_START + 1);
+    assert_eq!(suite.pending_payloads().pending_sudo_payloads, 3);
+
+    // The payload of the packet in flight during the migration is still resolved
+    let response = suite.resolve(1, REWARD, Outcome::Ack);
+    assert_eq!(attribute(&response, "msg").unwrap(), "reward delivered");
+    assert_eq!(suite.pending_payloads().pending_sudo_payloads, 2);
+}
+
+#[test]
+fn forwarded_transfers_carry_a_pfm_memo() {
+    let mut suite = setup();
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+    let carol = "carol".into_bech32_with_prefix("cosmos").to_string();
+
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.contract.clone(),
+            &ExecuteMsg::SetRoute {
+                route: Route {
+                    prefix: "cosmos".to_string(),
+                    channel_id: "channel-1".to_string(),
+                    forward: vec![ForwardHop {
+                        receiver: "hop".to_string(),
+                        port: None,
+                        channel: "channel-2".to_string(),
+                        timeout: None,
+                        retries: None,
+                    }],
+                },
+            },
+            &[],
+        )
+        .unwrap();
+    suite.create_survey("s1", &creator, 2);
+
+    // Direct routes send no memo, Neutron reports the outcome through sudo
+    let response = suite.pay_rewards("s1", &[&alice], "pay alice").unwrap();
+    assert_eq!(attribute(&response, "source_channel").unwrap(), CHANNEL);
+    assert_eq!(attribute(&response, "receiver").unwrap(), alice);
+    assert_eq!(attribute(&response, "memo").unwrap(), "");
+
+    let response = suite.pay_rewards("s1", &[&carol], "pay carol").unwrap();
+    assert_eq!(attribute(&response, "source_channel").unwrap(), "channel-1");
+    assert_eq!(attribute(&response, "receiver").unwrap(), "hop");
+    assert_eq!(
+        attribute(&response, "memo").unwrap(),
+        format!(
+            r#"{{"forward":{{"receiver":"{carol}","port":"transfer","channel":"channel-2"}}}}"#
+        )
+    );
+}
@@ -1,14 +1,14 @@
-use crate::error::ContractError;
+use crate::helpers::IbcTransferAdapter;
+use crate::ibc_lifecycle;
 use crate::msg::{ExecuteMsg, IBCLifecycleComplete, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg};
-use crate::query;
-use crate::state::{Config, CONFIG, MANAGERS};
-use crate::{execute, ibc_lifecycle};
+use crate::state::{IBC_TRANSFER_REPLY_ID, RECOVERY_STATES};
+use quizzler_core::state::{managers, Config, CONFIG, DEFAULT_IBC_TIMEOUT_SECONDS, ROUTES};
+use quizzler_core::{execute, helpers, migrate, query, ContractError};
 
-use crate::helpers;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
 };
 use cw2::set_contract_version;
 
@@ -26,16 +26,32 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    let cfg = helpers::map_validate(&msg.receiver_prefix, &msg.managers)?;
+    if msg.routes.is_empty() {
+        return Err(ContractError::InvalidConfig {
+            reason: "at least one route is required".to_string(),
+        });
+    }
+
+    for route in msg.routes.iter() {
+        helpers::validate_route(route)?;
+        ROUTES.save(deps.storage, &route.prefix, route)?;
+    }
+
+    let cfg = helpers::map_validate(deps.as_ref(), &msg.managers)?;
 
     for manager in cfg.iter() {
-        MANAGERS.save(deps.storage, &manager.address, manager)?;
+        managers().save(deps.storage, &manager.address, manager)?;
     }
 
+    let ibc_timeout_seconds = msg
+        .ibc_timeout_seconds
+        .unwrap_or(DEFAULT_IBC_TIMEOUT_SECONDS);
+    helpers::validate_ibc_timeout(ibc_timeout_seconds, msg.ibc_timeout_block.as_ref())?;
+
     let config = Config {
-        owner: info.sender,
-        receiver_prefix: msg.receiver_prefix,
-        channel_id: msg.channel_id,
+        owner: Some(info.sender),
+        ibc_timeout_seconds,
+        ibc_timeout_block: msg.ibc_timeout_block,
     };
 
     CONFIG.save(deps.storage, &config)?;
@@ -55,10 +71,20 @@ pub fn execute(
         ExecuteMsg::SetManagers {
             managers,
             pub_key,
+            key_type,
             status,
-        } => execute::set_manager((deps, &env, info), &managers, pub_key, status),
+        } => execute::set_manager(
+            (deps, &env, info),
+            &managers,
+            pub_key,
+            key_type.unwrap_or_default(),
+            status,
+        ),
+        ExecuteMsg::RemoveManager { manager } => {
+            execute::remove_manager((deps, &env, info), &manager)
+        }
         ExecuteMsg::CreateSurvey {
-            signature,
+            signatures,
             token,
             time_to_expire,
             owner,
@@ -67,10 +93,11 @@ pub fn execute(
             reward_denom,
             reward_amount,
             survey_hash,
-            manager_pub_key,
+            ends_at,
+            domain_version,
         } => execute::create_survey(
             (deps, &env, info),
-            signature,
+            signatures,
             token,
             time_to_expire,
             owner,
@@ -79,55 +106,116 @@ pub fn execute(
             reward_denom,
             reward_amount,
             survey_hash,
-            manager_pub_key,
+            ends_at,
+            domain_version.unwrap_or_default(),
+        ),
+        ExecuteMsg::FundSurvey {
+            signatures,
+            token,
+            time_to_expire,
+            survey_id,
+            additional_participants,
+            domain_version,
+        } => execute::fund_survey(
+            (deps, &env, info),
+            signatures,
+            token,
+            time_to_expire,
+            survey_id,
+            additional_participants,
+            domain_version.unwrap_or_default(),
         ),
         ExecuteMsg::CancelSurvey {
-            signature,
+            signatures,
             token,
             time_to_expire,
             survey_id,
-            manager_pub_key,
+            domain_version,
         } => execute::cancel_survey(
             (deps, &env, info),
-            signature,
+            &IbcTransferAdapter,
+            signatures,
             token,
             time_to_expire,
             survey_id,
-            manager_pub_key,
+            domain_version.unwrap_or_default(),
         ),
         ExecuteMsg::PayRewards {
-            signature,
+            signatures,
             token,
             time_to_expire,
             survey_ids,
             participants,
-            manager_pub_key,
+            domain_version,
         } => execute::pay_rewards(
             (deps, &env, info),
-            signature,
+            &IbcTransferAdapter,
+            signatures,
             token,
             time_to_expire,
             survey_ids,
             participants,
-            manager_pub_key,
+            domain_version.unwrap_or_default(),
         ),
-        ExecuteMsg::TransferOwnership { new_owner } => {
-            execute::transfer_ownership((deps, &env, info), new_owner)
+        ExecuteMsg::PruneProofTokens { limit } => {
+            execute::prune_proof_tokens((deps, &env, info), limit)
         }
+        ExecuteMsg::CloseExpiredSurvey { survey_id } => {
+            execute::close_expired_survey((deps, &env, info), &IbcTransferAdapter, survey_id)
+        }
+        ExecuteMsg::ProposeOwner { new_owner, expiry } => {
+            execute::propose_owner((deps, &env, info), new_owner, expiry)
+        }
+        ExecuteMsg::AcceptOwnership {} => execute::accept_ownership((deps, &env, info)),
+        ExecuteMsg::CancelOwnershipProposal {} => {
+            execute::cancel_ownership_proposal((deps, &env, info))
+        }
+        ExecuteMsg::RenounceOwnership {} => execute::renounce_ownership((deps, &env, info)),
+        ExecuteMsg::SweepSurplus { denom, recipient } => {
+            execute::sweep_surplus((deps, &env, info), denom, recipient)
+        }
+        ExecuteMsg::SetSigningDomain { version, enabled } => {
+            execute::set_signing_domain((deps, &env, info), version, enabled)
+        }
+        ExecuteMsg::SetSignaturePolicy { policy } => {
+            execute::set_signature_policy((deps, &env, info), policy)
+        }
+        ExecuteMsg::RemoveSignaturePolicy { denom } => {
+            execute::remove_signature_policy((deps, &env, info), denom)
+        }
+        ExecuteMsg::SetRoute { route } => execute::set_route((deps, &env, info), route),
+        ExecuteMsg::RemoveRoute { prefix } => execute::remove_route((deps, &env, info), prefix),
+        ExecuteMsg::UpdateConfig {
+            ibc_timeout_seconds,
+            ibc_timeout_block,
+        } => execute::update_config((deps, &env, info), ibc_timeout_seconds, ibc_timeout_block),
+        ExecuteMsg::Recover { addr, receiver } => execute::recover(
+            (deps, &env, info),
+            &IbcTransferAdapter,
+            &RECOVERY_STATES,
+            addr,
+            receiver,
+        ),
     }
 }
 
 /// Handling contract query
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::CancelSurveyProof {
             token,
             time_to_expire,
             survey_id,
+            domain_version,
         } => {
-            let query_resp =
-                query::cancel_survey_proof(token.as_str(), time_to_expire, survey_id.as_str())?;
+            let query_resp = query::cancel_survey_proof(
+                &env,
+                &domain_version.unwrap_or_default(),
+                token.as_str(),
+                time_to_expire,
+                survey_id.as_str(),
+            )?;
 
             to_json_binary(&query_resp)
         }
@@ -140,18 +228,41 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             reward_amount,
             survey_hash,
             reward_denom,
+            ends_at,
+            domain_version,
         } => {
-            let config = CONFIG.load(deps.storage)?;
-            let _ = helpers::validate_account(&config.receiver_prefix, &owner)?;
+            let _ = helpers::validate_account(deps, &owner)
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
             let query_resp = query::create_survey_proof(
-                token.as_str(),
+                &env,
+                &domain_version.unwrap_or_default(),
+                &token,
                 time_to_expire,
-                owner.as_str(),
-                survey_id.as_str(),
+                &owner,
+                &survey_id,
                 participants_limit,
                 reward_amount,
-                survey_hash,
-                reward_denom.as_str(),
+                &survey_hash,
+                &reward_denom,
+                ends_at,
+            )?;
+
+            to_json_binary(&query_resp)
+        }
+        QueryMsg::FundSurveyProof {
+            token,
+            time_to_expire,
+            survey_id,
+            additional_participants,
+            domain_version,
+        } => {
+            let query_resp = query::fund_survey_proof(
+                &env,
+                &domain_version.unwrap_or_default(),
+                &token,
+                time_to_expire,
+                &survey_id,
+                additional_participants,
             )?;
 
             to_json_binary(&query_resp)
@@ -161,9 +272,16 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             time_to_expire,
             survey_ids,
             participants,
+            domain_version,
         } => {
-            let query_resp =
-                query::pay_rewards_proof(token.as_str(), time_to_expire, survey_ids, participants)?;
+            let query_resp = query::pay_rewards_proof(
+                &env,
+                &domain_version.unwrap_or_default(),
+                token.as_str(),
+                time_to_expire,
+                survey_ids,
+                participants,
+            )?;
 
             to_json_binary(&query_resp)
         }
@@ -171,6 +289,16 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let resp = query::get_survey(deps, survey_id.as_str())?;
             to_json_binary(&resp)
         }
+        QueryMsg::ListSurveys {
+            start_after,
+            limit,
+            creator,
+            denom,
+            status,
+        } => {
+            let resp = query::list_surveys(deps, start_after, limit, creator, denom, status)?;
+            to_json_binary(&resp)
+        }
         QueryMsg::GetSurveyAmountToFund { survey_id } => {
             let amount = query::get_survey_amount_to_fund(deps, survey_id.as_str())?;
             to_json_binary(&amount)
@@ -179,10 +307,34 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let amount = query::get_survey_rewards_amount_paid(deps, survey_id.as_str())?;
             to_json_binary(&amount)
         }
+        QueryMsg::IsProofTokenUsed { token } => {
+            let used = query::is_proof_token_used(deps, &token)?;
+            to_json_binary(&used)
+        }
         QueryMsg::GetConfig {} => {
-            let config: Config = CONFIG.load(deps.storage)?;
+            let config: Config = query::get_config(deps)?;
             to_json_binary(&config)
         }
+        QueryMsg::GetOwnership {} => {
+            let ownership = query::get_ownership(deps)?;
+            to_json_binary(&ownership)
+        }
+        QueryMsg::GetSolvency { denom } => {
+            let resp = query::get_solvency(deps, &env, denom)?;
+            to_json_binary(&resp)
+        }
+        QueryMsg::ListRoutes {} => {
+            let routes = query::list_routes(deps)?;
+            to_json_binary(&routes)
+        }
+        QueryMsg::ListSignaturePolicies {} => {
+            let policies = query::list_signature_policies(deps)?;
+            to_json_binary(&policies)
+        }
+        QueryMsg::ListSigningDomains {} => {
+            let domains = query::list_signing_domains(deps)?;
+            to_json_binary(&domains)
+        }
         QueryMsg::GetHasClaimedReward {
             survey_id,
             participant,
@@ -191,12 +343,34 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 query::get_has_claimed_reward(deps, survey_id.as_str(), participant.as_str())?;
             to_json_binary(&has_claimed)
         }
+        QueryMsg::ListRewardedParticipants {
+            survey_id,
+            start_after,
+            limit,
+        } => {
+            let resp = query::list_rewarded_participants(deps, &survey_id, start_after, limit)?;
+            to_json_binary(&resp)
+        }
+        QueryMsg::ListParticipantRewards {
+            participant,
+            start_after,
+            limit,
+        } => {
+            let resp = query::list_participant_rewards(deps, &participant, start_after, limit)?;
+            to_json_binary(&resp)
+        }
+        QueryMsg::GetRecoveries { addr } => {
+            let recoveries = query::get_recoveries(deps, &RECOVERY_STATES, &addr)?;
+            to_json_binary(&recoveries)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
-    match msg {}
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    migrate::migrate_state(deps.storage, &env)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -217,9 +391,9 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
 /// Handling submessage reply.
 /// For more info on submessage and reply, see https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#submessages
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, _msg: Reply) -> Result<Response, ContractError> {
-    // With `Response` type, it is still possible to dispatch message to invoke external logic.
-    // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
-
-    return Ok(Response::new());
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        IBC_TRANSFER_REPLY_ID => ibc_lifecycle::save_inflight_packet(deps, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
 }
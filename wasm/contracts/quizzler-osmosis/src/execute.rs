@@ -1,444 +0,0 @@
-use crate::error::ContractError;
-use crate::helpers;
-use crate::msg::{CancelSurveyResponse, CreateSurveyResponse, PayRewardsResponse};
-use crate::query;
-use crate::state::{
-    Config, ManagerInfo, SurveyInfo, CONFIG, MANAGERS, SURVEYS, SURVEY_REWARDED_USERS,
-};
-use cosmwasm_std::{
-    to_json_binary, Binary, Coin, CosmosMsg, DepsMut, Env, MessageInfo, Response, Uint256,
-};
-
-#[allow(clippy::too_many_arguments)]
-pub fn create_survey(
-    ctx: (DepsMut, &Env, MessageInfo),
-    signature: String,
-    token: String,
-    time_to_expire: u64,
-    owner: String,
-    survey_id: String,
-    participants_limit: u32,
-    reward_denom: String,
-    reward_amount: u128,
-    survey_hash: String,
-    manager_pub_key: String,
-) -> Result<Response, ContractError> {
-    let (mut deps, env, info) = ctx;
-
-    if SURVEYS.has(deps.storage, &survey_id) {
-        return Err(ContractError::SurveyAlreadyExists {});
-    }
-
-    if reward_amount == 0 {
-        return Err(ContractError::InvalidRewardAmount {});
-    }
-
-    if participants_limit == 0 {
-        return Err(ContractError::CustomError {
-            val: "Participants limit must be greater than 0".to_string(),
-        });
-    }
-
-    let config = CONFIG.load(deps.storage)?;
-
-    let message_hash = query::create_survey_proof(
-        &token,
-        time_to_expire,
-        &owner,
-        &survey_id,
-        participants_limit,
-        reward_amount,
-        survey_hash.clone(),
-        &reward_denom,
-    )?;
-
-    helpers::auth_validations(
-        &mut deps,
-        env,
-        token.clone(),
-        message_hash,
-        manager_pub_key,
-        time_to_expire,
-        signature,
-    )?;
-
-    let (_, validated_owner_addr) = helpers::validate_account(&config.receiver_prefix, &owner)?;
-
-    // Save survey info
-    let survey_info = SurveyInfo {
-        survey_creator: validated_owner_addr,
-        participants_limit,
-        reward_denom: reward_denom.clone(),
-        reward_amount,
-        participants_rewarded: 0,
-        survey_hash,
-        is_cancelled: false,
-    };
-
-    let amount_sent = cw_utils::must_pay(&info, &reward_denom)?;
-
-    let amount_to_survey = (participants_limit as u128)
-        .checked_mul(reward_amount)
-        .ok_or(ContractError::ArithmeticError {})?;
-
-    // Ensure exact funding - prevent both underfunding and overfunding
-    let amount_to_survey_uint256 = Uint256::from_uint128(amount_to_survey.into());
-    if amount_sent < amount_to_survey_uint256 {
-        return Err(ContractError::CustomError {
-            val: format!(
-                "Exact funding required: expected {} {}, got {}",
-                amount_to_survey, reward_denom, amount_sent
-            ),
-        });
-    }
-
-    SURVEYS.save(deps.storage, &survey_id, &survey_info)?;
-
-    let response_data = CreateSurveyResponse::new(
-        &survey_id,
-        participants_limit,
-        reward_amount,
-        &reward_denom,
-        env.block.time.seconds(),
-    );
-
-    Ok(Response::new()
-        .set_data(to_json_binary(&response_data)?)
-        .add_attribute("action", "create_survey")
-        .add_attribute("survey_id", survey_id)
-        .add_attribute("owner", owner)
-        .add_attribute("amount_funded", amount_sent.to_string())
-        .add_attribute("denom", reward_denom))
-}
-
-#[allow(clippy::too_many_arguments)]
-pub fn cancel_survey(
-    ctx: (DepsMut, &Env, MessageInfo),
-    signature: String,
-    token: String,
-    time_to_expire: u64,
-    survey_id: String,
-    manager_pub_key: String,
-) -> Result<Response, ContractError> {
-    let (mut deps, env, _info) = ctx;
-    let message_hash = query::cancel_survey_proof(&token, time_to_expire, &survey_id)?;
-
-    helpers::auth_validations(
-        &mut deps,
-        env,
-        token.clone(),
-        message_hash,
-        manager_pub_key,
-        time_to_expire,
-        signature,
-    )?;
-
-    // Mark survey as cancelled
-    let survey_info = SURVEYS.update(
-        deps.storage,
-        &survey_id,
-        |survey_info| -> Result<SurveyInfo, ContractError> {
-            let mut survey = survey_info.ok_or(ContractError::SurveyNotFound {})?;
-            if survey.is_cancelled {
-                return Err(ContractError::SurveyAlreadyCancelled {});
-            }
-            survey.is_cancelled = true;
-            Ok(survey)
-        },
-    )?;
-
-    let reward_denom = survey_info.reward_denom;
-
-    let funded_amount = query::get_survey_amount_to_fund(deps.as_ref(), &survey_id)?;
-
-    let paid_amount = query::get_survey_rewards_amount_paid(deps.as_ref(), &survey_id)?;
-
-    // This should never happen - indicates critical accounting error
-    if paid_amount > funded_amount {
-        return Err(ContractError::CustomError {
-            val: format!(
-                "Critical accounting error: paid amount ({}) exceeds funded amount ({})",
-                paid_amount, funded_amount
-            ),
-        });
-    }
-
-    let return_amount = funded_amount - paid_amount;
-
-    let bal = helpers::query_contract_balance(&deps.querier, &env.contract.address, &reward_denom)?;
-
-    if bal < Uint256::from_uint128(return_amount.into()) {
-        return Err(ContractError::InsufficientContractBalance {});
-    }
-
-    if return_amount == 0 {
-        return Err(ContractError::NothingToRefund {});
-    }
-
-    let coin = Coin::new(return_amount, &reward_denom);
-
-    let ibc_msg = CosmosMsg::Ibc(helpers::create_ibc_transfer(
-        deps.as_ref(),
-        env,
-        &survey_info.survey_creator.to_string(),
-        coin,
-    )?);
-
-    let response_data =
-        CancelSurveyResponse::new(&survey_id, return_amount, env.block.time.seconds());
-
-    Ok(Response::new()
-        .set_data(to_json_binary(&response_data)?)
-        .add_message(ibc_msg)
-        .add_attribute("action", "cancel_survey")
-        .add_attribute("amount", return_amount.to_string())
-        .add_attribute("denom", reward_denom)
-        .add_event(helpers::ibc_message_event("cancel_survey: refund creator")))
-}
-
-#[allow(clippy::too_many_arguments)]
-pub fn pay_rewards(
-    ctx: (DepsMut, &Env, MessageInfo),
-    signature: String,
-    token: String,
-    time_to_expire: u64,
-    survey_ids: Vec<String>,
-    participants: Vec<String>,
-    manager_pub_key: String,
-) -> Result<Response, ContractError> {
-    let (mut deps, env, _info) = ctx;
-
-    if survey_ids.len() != participants.len() {
-        return Err(ContractError::ArrayLengthMismatch {});
-    }
-
-    if survey_ids.is_empty() {
-        return Err(ContractError::CustomError {
-            val: "Empty arrays not allowed".to_string(),
-        });
-    }
-
-    let config = CONFIG.load(deps.storage)?;
-
-    let message_hash = query::pay_rewards_proof(
-        &token,
-        time_to_expire,
-        survey_ids.clone(),
-        participants.clone(),
-    )?;
-
-    helpers::auth_validations(
-        &mut deps,
-        env,
-        token.clone(),
-        message_hash,
-        manager_pub_key,
-        time_to_expire,
-        signature,
-    )?;
-
-    // Calculate total rewards needed and verify contract balance
-    let mut total_rewards_needed = 0u128;
-    let mut reward_denom: Option<String> = None;
-
-    for survey_id in &survey_ids {
-        let survey_info = SURVEYS.load(deps.storage, survey_id)?;
-
-        if survey_info.is_cancelled {
-            return Err(ContractError::SurveyAlreadyCancelled {});
-        }
-
-        if survey_info.participants_rewarded >= survey_info.participants_limit {
-            return Err(ContractError::AllParticipantsRewarded {});
-        }
-
-        total_rewards_needed = total_rewards_needed
-            .checked_add(survey_info.reward_amount)
-            .ok_or(ContractError::ArithmeticError {})?;
-
-        // Ensure all surveys use the same reward_denom
-        match &reward_denom {
-            None => reward_denom = Some(survey_info.reward_denom.clone()),
-            Some(denom) => {
-                if denom != &survey_info.reward_denom {
-                    return Err(ContractError::CustomError {
-                        val: "All surveys in batch must use same reward denomination".to_string(),
-                    });
-                }
-            }
-        }
-    }
-
-    let reward_denom = reward_denom.ok_or(ContractError::CustomError {
-        val: "No reward denomination found".to_string(),
-    })?;
-
-    // Check contract has sufficient balance for all rewards
-    let contract_balance =
-        helpers::query_contract_balance(&deps.querier, &env.contract.address, &reward_denom)?;
-
-    if contract_balance < Uint256::from_uint128(total_rewards_needed.into()) {
-        return Err(ContractError::InsufficientContractBalance {});
-    }
-
-    let mut messages: Vec<CosmosMsg> = Vec::new();
-    let mut rewards = 0u128;
-
-    for i in 0..survey_ids.len() {
-        let survey_id = &survey_ids[i];
-
-        let (_, participant) =
-            helpers::validate_account(&config.receiver_prefix, &participants[i])?;
-
-        let already_rewarded = SURVEY_REWARDED_USERS
-            .load(deps.storage, (survey_id.as_str(), &participant))
-            .unwrap_or(false);
-
-        if already_rewarded {
-            return Err(ContractError::UserAlreadyRewarded {});
-        }
-
-        let mut survey_info = SURVEYS.load(deps.storage, survey_id)?;
-        let survey_reward_denom = survey_info.reward_denom.clone();
-        let reward_amount = survey_info.reward_amount;
-
-        // Double-check survey state before creating transfer
-        if survey_info.is_cancelled {
-            return Err(ContractError::SurveyAlreadyCancelled {});
-        }
-
-        if survey_info.participants_rewarded >= survey_info.participants_limit {
-            return Err(ContractError::AllParticipantsRewarded {});
-        }
-
-        // Validate the reward amount hasn't been corrupted
-        if reward_amount == 0 {
-            return Err(ContractError::CustomError {
-                val: format!("Invalid reward amount (0) for survey {}", survey_id),
-            });
-        }
-
-        // Ensure we're transferring exactly what was promised, no more
-        let actual_transfer_amount = reward_amount;
-
-        // Create IBC transfer message with explicit amount validation
-        let transfer_coin = Coin::new(actual_transfer_amount, &survey_reward_denom);
-
-        // Final sanity check: ensure coin amount matches expected reward
-        // In cosmwasm-std 3.0, Coin.amount is Uint256
-        let expected_amount: Uint256 = Uint256::from_uint128(actual_transfer_amount.into());
-        if transfer_coin.amount != expected_amount {
-            return Err(ContractError::CustomError {
-                val: format!(
-                    "Transfer amount mismatch: expected {}, got {}",
-                    reward_amount, transfer_coin.amount
-                ),
-            });
-        }
-
-        let ibc_transfer_message = CosmosMsg::Ibc(helpers::create_ibc_transfer(
-            deps.as_ref(),
-            env,
-            &participant.to_string(),
-            transfer_coin,
-        )?);
-
-        messages.push(ibc_transfer_message);
-
-        rewards += actual_transfer_amount;
-
-        survey_info.participants_rewarded += 1;
-
-        SURVEYS.save(deps.storage, survey_id, &survey_info)?;
-
-        // mark user as rewarded
-        SURVEY_REWARDED_USERS.save(deps.storage, (survey_id.as_str(), &participant), &true)?;
-    }
-
-    let response_data = PayRewardsResponse::new(
-        survey_ids,
-        rewards,
-        participants.clone(),
-        env.block.time.seconds(),
-    );
-
-    Ok(Response::new()
-        .set_data(to_json_binary(&response_data)?)
-        .add_messages(messages)
-        .add_attribute("action", "pay_rewards")
-        .add_attribute("number_of_rewards", participants.len().to_string())
-        .add_event(helpers::ibc_message_event(
-            "pay_rewards: distribute survey rewards",
-        )))
-}
-
-pub fn set_manager(
-    ctx: (DepsMut, &Env, MessageInfo),
-    manager_addr: &str,
-    pub_key: String,
-    status: bool,
-) -> Result<Response, ContractError> {
-    let (deps, _env, info) = ctx;
-    let sender = info.sender;
-
-    helpers::check_is_contract_owner(deps.as_ref(), sender)?;
-
-    let config = CONFIG.load(deps.storage)?;
-
-    let (_, manager_addr) = helpers::validate_account(&config.receiver_prefix, &manager_addr)?;
-
-    let enc_pub_key = Binary::from_base64(&pub_key)?;
-
-    if MANAGERS.has(deps.storage, &manager_addr) {
-        MANAGERS.update(
-            deps.storage,
-            &manager_addr,
-            |manager_info| -> Result<ManagerInfo, ContractError> {
-                if let Some(mut info) = manager_info {
-                    info.status = status;
-                    info.pub_key = enc_pub_key;
-                    Ok(info)
-                } else {
-                    Err(ContractError::InvalidManager {})
-                }
-            },
-        )?;
-    } else {
-        let manager_info = ManagerInfo {
-            address: manager_addr.clone(),
-            pub_key: enc_pub_key,
-            status,
-        };
-        MANAGERS.save(deps.storage, &manager_addr, &manager_info)?;
-    }
-
-    Ok(Response::new()
-        .add_attribute("action", "set_manager")
-        .add_attribute("manager", manager_addr.to_string())
-        .add_attribute("pub_key", pub_key)
-        .add_attribute("status", status.to_string()))
-}
-
-// Transfer ownership of this contract
-pub fn transfer_ownership(
-    ctx: (DepsMut, &Env, MessageInfo),
-    new_owner: String,
-) -> Result<Response, ContractError> {
-    // only owner can transfer
-    let (deps, _env, info) = ctx;
-    let sender = info.sender;
-    helpers::check_is_contract_owner(deps.as_ref(), sender)?;
-    let new_owner = deps.api.addr_validate(&new_owner)?;
-
-    CONFIG.update(
-        deps.storage,
-        |mut config| -> Result<Config, ContractError> {
-            config.owner = new_owner.clone();
-            Ok(config)
-        },
-    )?;
-
-    Ok(Response::new()
-        .add_attribute("action", "transfer_ownership")
-        .add_attribute("new_owner", new_owner.to_string()))
-}
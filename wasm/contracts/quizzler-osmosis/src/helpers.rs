@@ -1,144 +1,97 @@
-use crate::error::ContractError;
-use crate::msg::Manager;
-use crate::state::{ManagerInfo, CONFIG, MANAGERS, USED_PROOF_TOKENS};
+use crate::state::{ibc, IBC_TRANSFER_REPLY_ID};
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Addr, BalanceResponse, BankQuery, Binary, Coin, Deps, DepsMut, Env, IbcMsg, IbcTimeout,
-    QuerierWrapper, QueryRequest, StdResult, Uint256,
+    to_json_binary, to_json_string, Deps, DepsMut, Env, IbcMsg, IbcTimeout, StdResult, SubMsg,
 };
-
-pub fn map_validate(receiver_prefix: &str, managers: &[Manager]) -> StdResult<Vec<ManagerInfo>> {
-    managers
-        .iter()
-        .map(|admin| {
-            let (_, validated_addr) = validate_account(receiver_prefix, &admin.addr)?;
-            let pub_key = Binary::from_base64(&admin.pub_key)?;
-            Ok(ManagerInfo {
-                address: validated_addr,
-                pub_key: pub_key,
-                status: true,
-            })
-        })
-        .collect()
-}
-
-pub fn validate_account(
-    receiver_prefix: &str,
-    receiver: &str,
-) -> StdResult<(String, Addr), ContractError> {
-    let Ok((prefix, _, _)) = bech32::decode(receiver) else {
-        return Err(ContractError::InvalidAccount {
-            receiver: receiver.to_string(),
-        });
-    };
-
-    if prefix != receiver_prefix {
-        return Err(ContractError::ExpectedAgoricAccount {
-            receiver: receiver.to_string(),
-        });
+use quizzler_core::forward::{forwarding, ForwardMemo};
+use quizzler_core::msg::Route;
+use quizzler_core::state::{Config, CONFIG};
+use quizzler_core::transfer::{Transfer, TransferAdapter};
+use quizzler_core::ContractError;
+
+/// Sends survey payouts over ICS-20 using the native `IbcMsg::Transfer`.
+///
+/// Every transfer is dispatched as a submessage so the reply can record the packet in
+/// `INFLIGHT_PACKETS` once its sequence is known.
+pub struct IbcTransferAdapter;
+
+impl TransferAdapter for IbcTransferAdapter {
+    fn create_transfer(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        transfer: Transfer,
+        route: Route,
+    ) -> Result<SubMsg, ContractError> {
+        let ibc_msg = create_ibc_transfer(deps.as_ref(), env, &transfer, &route)?;
+
+        // The sequence is filled in by the reply handler
+        let inflight_packet = ibc::IBCTransfer {
+            recovery_addr: transfer.recipient,
+            survey_id: transfer.survey_id,
+            kind: transfer.kind,
+            channel_id: route.channel_id,
+            sequence: 0,
+            amount: transfer.coin.amount.u128(),
+            denom: transfer.coin.denom,
+            status: ibc::PacketLifecycleStatus::Sent,
+        };
+
+        Ok(SubMsg::reply_on_success(ibc_msg, IBC_TRANSFER_REPLY_ID)
+            .with_payload(to_json_binary(&inflight_packet)?))
     }
-
-    Ok((prefix, Addr::unchecked(receiver)))
 }
 
-pub fn auth_validations(
-    deps: &mut DepsMut,
+pub fn create_ibc_transfer(
+    deps: Deps,
     env: &Env,
-    token: String,
-    message: Binary,
-    pub_key: String,
-    time_to_expire: u64,
-    signature: String,
-) -> StdResult<(), ContractError> {
-    let pub_key = Binary::from_base64(&pub_key)?;
-    let signature = Binary::from_base64(&signature)?;
-
-    let current_block_time = env.block.time.seconds();
-    if time_to_expire < current_block_time {
-        return Err(ContractError::ProofExpired {});
-    }
-
-    let proof_token_exists = USED_PROOF_TOKENS
-        .load(deps.storage, &token)
-        .unwrap_or(false);
-
-    if proof_token_exists {
-        return Err(ContractError::TokenAlreadyUsed {});
-    }
-
-    // Verify pub key exists
-    let managers = MANAGERS
-        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
-        .collect::<StdResult<Vec<(Addr, ManagerInfo)>>>()?;
-
-    let mut pub_key_exists = false;
-
-    for (_addr, manager_info) in managers.iter() {
-        if manager_info.pub_key == pub_key && manager_info.status {
-            pub_key_exists = true;
-            break;
-        }
-    }
+    transfer: &Transfer,
+    route: &Route,
+) -> Result<IbcMsg, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
 
-    if !pub_key_exists {
-        return Err(ContractError::InvalidSigner {});
-    }
+    let receiver = transfer.recipient.to_string();
 
-    let result = deps.api.ed25519_verify(&message, &signature, &pub_key)?;
+    // Multi-hop routes address the packet to the first hop, which forwards it on
+    let (to_address, forward) = match forwarding(route, &receiver) {
+        Some(forwarding) => (forwarding.receiver, Some(forwarding.forward)),
+        None => (receiver, None),
+    };
 
-    if !result {
-        return Err(ContractError::InvalidMessageHash {});
-    }
+    let timeout = ibc_timeout(&config, env);
 
-    // mark proof token as used only after successful verification
-    USED_PROOF_TOKENS.save(deps.storage, &token, &true)?;
+    let ibc_transfer_msg = IbcMsg::Transfer {
+        channel_id: route.channel_id.clone(),
+        to_address,
+        amount: transfer.coin.clone(),
+        timeout,
+        memo: Some(transfer_memo(env, forward)?),
+    };
 
-    Ok(())
+    Ok(ibc_transfer_msg)
 }
 
-pub fn check_is_contract_owner(deps: Deps, sender: Addr) -> Result<(), ContractError> {
-    let config = CONFIG.load(deps.storage).unwrap();
-    if config.owner != sender {
-        Err(ContractError::Unauthorized {})
-    } else {
-        Ok(())
+pub fn ibc_timeout(config: &Config, env: &Env) -> IbcTimeout {
+    let timestamp = env.block.time.plus_seconds(config.ibc_timeout_seconds);
+    match config.ibc_timeout_block {
+        Some(block) => IbcTimeout::with_both(block, timestamp),
+        None => IbcTimeout::with_timestamp(timestamp),
     }
 }
 
-pub fn query_contract_balance(
-    querier: &QuerierWrapper,
-    addr: &Addr,
-    denom: &str,
-) -> StdResult<Uint256> {
-    let resp: BalanceResponse = querier.query(&QueryRequest::Bank(BankQuery::Balance {
-        address: addr.to_string(),
-        denom: denom.to_string(),
-    }))?;
-    Ok(resp.amount.amount)
-}
-
-pub fn create_ibc_transfer(
-    deps: Deps,
-    env: &Env,
-    receiver: &str,
-    coin: Coin,
-) -> StdResult<IbcMsg, ContractError> {
-    let config = CONFIG.load(deps.storage).unwrap();
-
-    let _ = validate_account(&config.receiver_prefix, receiver)?;
-
-    let ibc_transfer_msg = IbcMsg::Transfer {
-        channel_id: config.channel_id,
-        to_address: receiver.to_string(),
-        amount: coin,
-        timeout: IbcTimeout::with_timestamp(
-            env.block.time.plus_seconds(600), // 10 minutes
-        ),
-        memo: Option::None,
-    };
-
-    Ok(ibc_transfer_msg)
+#[cw_serde]
+struct TransferMemo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forward: Option<ForwardMemo>,
+    ibc_callback: String,
 }
 
-pub fn ibc_message_event(context: &str) -> cosmwasm_std::Event {
-    cosmwasm_std::Event::new("ibc_message_added").add_attribute("context", context)
+/// Memo asking Osmosis' IBC hooks to report the ack or timeout of the packet back to
+/// this contract through the `ibc_lifecycle_complete` sudo message, along with the
+/// packet-forward-middleware instructions of multi-hop routes.
+pub fn transfer_memo(env: &Env, forward: Option<ForwardMemo>) -> StdResult<String> {
+    to_json_string(&TransferMemo {
+        forward,
+        ibc_callback: env.contract.address.to_string(),
+    })
 }
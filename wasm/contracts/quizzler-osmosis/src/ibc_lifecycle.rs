@@ -1,11 +1,52 @@
-use cosmwasm_std::{Addr, DepsMut, Response};
+use cosmwasm_std::{from_json, Addr, DepsMut, Reply, Response};
+use prost::Message;
+use quizzler_core::transfer;
 
+use crate::msg::MsgTransferResponse;
 use crate::state;
 use crate::{
     state::{INFLIGHT_PACKETS, RECOVERY_STATES},
     ContractError,
 };
 
+/// Handles the reply of a transfer submessage.
+///
+/// The packet sequence is only known once the transfer has been executed, so the
+/// in-flight packet carried in the submessage payload is stored here under its
+/// (source_channel, sequence) for `receive_ack` and `receive_timeout` to find.
+pub fn save_inflight_packet(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let result = msg
+        .result
+        .into_result()
+        .map_err(|error| ContractError::ReplyError { error })?;
+
+    let transfer_response = result
+        .msg_responses
+        .first()
+        .ok_or(ContractError::NoDataInReply {})?;
+
+    let MsgTransferResponse { sequence } =
+        MsgTransferResponse::decode(transfer_response.value.as_slice()).map_err(|err| {
+            ContractError::ReplyError {
+                error: format!("failed to parse MsgTransferResponse: {err}"),
+            }
+        })?;
+
+    let mut inflight_packet: state::ibc::IBCTransfer = from_json(&msg.payload)?;
+    inflight_packet.sequence = sequence;
+
+    INFLIGHT_PACKETS.save(
+        deps.storage,
+        (&inflight_packet.channel_id, sequence),
+        &inflight_packet,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "save_inflight_packet")
+        .add_attribute("channel", inflight_packet.channel_id)
+        .add_attribute("sequence", sequence.to_string()))
+}
+
 // Store a RECOVERY_STATE for the failed ibc packet
 fn create_recovery(
     deps: DepsMut,
@@ -15,6 +56,9 @@ fn create_recovery(
     let mut recovery = inflight_packet; // Recoveries are just inflight packets ready to be recovered
     let recovery_addr = recovery.recovery_addr.clone();
 
+    // The funds are back in the contract and owed to the recovery address
+    transfer::record_liability(deps.storage, &recovery.denom, recovery.amount)?;
+
     RECOVERY_STATES.update(deps.storage, &recovery_addr, |recoveries| {
         // Since the recovery state and the in-flight packet store the same
         // data, we can just modify the status and store the object in the
@@ -44,9 +88,10 @@ fn create_recovery(
 /// If it's a success, we remove the inflight packet and return. The packet will
 /// no longer be tracked.
 ///
-/// If it's a failure, the sent funds will have been returned to this contract.
-/// We then store the amount and original sender on RECOVERY_STATES so that the
-/// sender can recover the funds by calling execute::Recover{}.
+/// If it's a failure, the sent funds will have been returned to this contract. This
+/// includes a failure on any hop of a forwarded transfer, which packet-forward-middleware
+/// reports as an error ack of the first packet. We then store the amount and original sender on RECOVERY_STATES so that the
+/// sender can recover the funds by calling ExecuteMsg::Recover {}.
 pub fn receive_ack(
     deps: DepsMut,
     source_channel: String,
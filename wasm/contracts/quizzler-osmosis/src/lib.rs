@@ -1,11 +1,11 @@
 pub mod contract;
-mod error;
-pub mod execute;
 mod helpers;
 mod ibc_lifecycle;
 pub mod msg;
-pub mod query;
 pub mod state;
 
-pub use crate::error::ContractError;
+#[cfg(test)]
+mod tests;
+
 pub use crate::msg::ExecuteMsg;
+pub use quizzler_core::ContractError;
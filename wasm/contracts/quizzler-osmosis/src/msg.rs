@@ -1,18 +1,23 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Binary;
+use cosmwasm_std::{Binary, IbcTimeoutBlock};
+use quizzler_core::state::{DomainVersion, KeyType, SurveyStatus};
 
-#[cw_serde]
-pub struct Manager {
-    pub addr: String,
-    pub pub_key: String,
-}
+pub use quizzler_core::msg::{
+    CancelSurveyResponse, CreateSurveyResponse, FundSurveyResponse, Manager, ManagerSignature,
+    OwnershipResponse, ParticipantRewardResponse, PayRewardsResponse, Route, SignaturePolicy,
+    SigningDomainResponse, SolvencyResponse, SurveyResponse,
+};
 
 /// Message type for `instantiate` entry_point
 #[cw_serde]
 pub struct InstantiateMsg {
+    /// Validated against `routes`, so they need a routed prefix
     pub managers: Vec<Manager>,
-    pub receiver_prefix: String,
-    pub channel_id: String,
+    /// Channels used to pay each bech32 prefix
+    pub routes: Vec<Route>,
+    /// Defaults to `DEFAULT_IBC_TIMEOUT_SECONDS`
+    pub ibc_timeout_seconds: Option<u64>,
+    pub ibc_timeout_block: Option<IbcTimeoutBlock>,
 }
 
 /// Message type for `execute` entry_point
@@ -21,10 +26,17 @@ pub enum ExecuteMsg {
     SetManagers {
         managers: String,
         pub_key: String,
+        /// Defaults to ed25519
+        key_type: Option<KeyType>,
         status: bool,
     },
+    /// Owner only. Deletes a manager, unlike `SetManagers` with `status: false`
+    RemoveManager {
+        manager: String,
+    },
     CreateSurvey {
-        signature: String,
+        /// Manager signatures over the proof, see `ListSignaturePolicies` for how many
+        signatures: Vec<ManagerSignature>,
         token: String,
         time_to_expire: u64,
         owner: String,
@@ -33,47 +45,112 @@ pub enum ExecuteMsg {
         reward_denom: String,
         reward_amount: u128,
         survey_hash: String,
-        manager_pub_key: String,
+        /// Optional deadline (block time in seconds), signed into the create proof
+        ends_at: Option<u64>,
+        /// Proof format signed by the managers, defaults to V1
+        domain_version: Option<DomainVersion>,
+    },
+    /// Raises the participants limit of a survey. Must be sent with exactly
+    /// `additional_participants * reward_amount` of the survey's reward denom.
+    FundSurvey {
+        signatures: Vec<ManagerSignature>,
+        token: String,
+        time_to_expire: u64,
+        survey_id: String,
+        additional_participants: u32,
+        domain_version: Option<DomainVersion>,
     },
     CancelSurvey {
-        signature: String,
+        signatures: Vec<ManagerSignature>,
         token: String,
         time_to_expire: u64,
         survey_id: String,
-        manager_pub_key: String,
+        domain_version: Option<DomainVersion>,
     },
     PayRewards {
-        signature: String,
+        signatures: Vec<ManagerSignature>,
         token: String,
         time_to_expire: u64,
         survey_ids: Vec<String>,
         participants: Vec<String>,
-        manager_pub_key: String,
+        domain_version: Option<DomainVersion>,
     },
-    TransferOwnership {
+    /// Deletes up to `limit` used proof tokens whose proofs have expired. Callable by
+    /// anyone.
+    PruneProofTokens {
+        limit: Option<u32>,
+    },
+    /// Refunds the unspent funding of a survey whose `ends_at` has passed to its creator.
+    /// Callable by anyone.
+    CloseExpiredSurvey {
+        survey_id: String,
+    },
+    /// Owner only. `new_owner` becomes owner once they send `AcceptOwnership`, before
+    /// `expiry` (block time in seconds) when set.
+    ProposeOwner {
         new_owner: String,
+        expiry: Option<u64>,
+    },
+    AcceptOwnership {},
+    /// Owner only. Withdraws the pending `ProposeOwner`.
+    CancelOwnershipProposal {},
+    /// Owner only. Leaves the contract without an owner, for good.
+    RenounceOwnership {},
+    /// Owner only. Sends the balance of `denom` that is not owed to any survey or
+    /// recovery to `recipient`.
+    SweepSurplus {
+        denom: String,
+        recipient: String,
+    },
+    /// Owner only. Enables a signing domain version next to the others, or retires it.
+    SetSigningDomain {
+        version: DomainVersion,
+        enabled: bool,
+    },
+    /// Owner only. Requires `policy.threshold` manager signatures for operations moving
+    /// more than `policy.value_limit` of `policy.denom`.
+    SetSignaturePolicy {
+        policy: SignaturePolicy,
+    },
+    /// Owner only. Operations in `denom` need a single manager signature again.
+    RemoveSignaturePolicy {
+        denom: String,
+    },
+    /// Owner only. Pays accounts with `route.prefix` over `route.channel_id`.
+    SetRoute {
+        route: Route,
+    },
+    /// Owner only. Accounts with `prefix` can no longer be paid.
+    RemoveRoute {
+        prefix: String,
+    },
+    /// Owner only. Replaces the timeouts of outgoing IBC transfers.
+    UpdateConfig {
+        ibc_timeout_seconds: u64,
+        ibc_timeout_block: Option<IbcTimeoutBlock>,
+    },
+    /// Re-sends every failed or timed out transfer recorded for `addr`.
+    ///
+    /// `addr` defaults to the sender; recovering for someone else requires the owner or
+    /// an active manager. Funds go back to `addr` unless `receiver` is set, which only
+    /// `addr` itself or the owner may do. Accounts on other chains can't send this message,
+    /// so their funds are redirected by the owner once the account holder asks for it.
+    Recover {
+        addr: Option<String>,
+        receiver: Option<String>,
     },
 }
 
 /// Message type for `migrate` entry_point
 #[cw_serde]
-pub enum MigrateMsg {}
-
-#[cw_serde]
-pub struct SurveyResponse {
-    pub survey_creator: String,
-    pub participants_limit: u32,
-    pub reward_amount: u128,
-    pub participants_rewarded: u32,
-    pub survey_hash: String,
-    pub amount_to_fund: u128,
-    pub is_cancelled: bool,
-}
+pub struct MigrateMsg {}
 
 /// Message type for `query` entry_point
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    /// Hash for managers to sign. Proofs are bound to this chain and contract, so they
+    /// must be fetched from the deployment they are meant for.
     #[returns(Binary)]
     CreateSurveyProof {
         token: String,
@@ -84,12 +161,23 @@ pub enum QueryMsg {
         reward_amount: u128,
         survey_hash: String,
         reward_denom: String,
+        ends_at: Option<u64>,
+        domain_version: Option<DomainVersion>,
     },
     #[returns(Binary)]
     CancelSurveyProof {
         token: String,
         time_to_expire: u64,
         survey_id: String,
+        domain_version: Option<DomainVersion>,
+    },
+    #[returns(Binary)]
+    FundSurveyProof {
+        token: String,
+        time_to_expire: u64,
+        survey_id: String,
+        additional_participants: u32,
+        domain_version: Option<DomainVersion>,
     },
     #[returns(Binary)]
     PayRewardsProof {
@@ -97,20 +185,63 @@ pub enum QueryMsg {
         time_to_expire: u64,
         survey_ids: Vec<String>,
         participants: Vec<String>,
+        domain_version: Option<DomainVersion>,
     },
     #[returns(SurveyResponse)]
     GetSurvey { survey_id: String },
+    /// Pages through surveys ordered by id, optionally filtered by creator, reward denom
+    /// and status.
+    #[returns(Vec<SurveyResponse>)]
+    ListSurveys {
+        start_after: Option<String>,
+        limit: Option<u32>,
+        creator: Option<String>,
+        denom: Option<String>,
+        status: Option<SurveyStatus>,
+    },
     #[returns(u128)]
     GetSurveyAmountToFund { survey_id: String },
     #[returns(u128)]
     GetSurveyRewardsAmountPaid { survey_id: String },
-    #[returns(crate::state::Config)]
+    /// Whether `token` has been used by a proof that has not been pruned yet
+    #[returns(bool)]
+    IsProofTokenUsed { token: String },
+    #[returns(quizzler_core::state::Config)]
     GetConfig {},
+    /// Current owner and the pending ownership proposal
+    #[returns(OwnershipResponse)]
+    GetOwnership {},
+    /// Contract balance of `denom` against the funds committed to surveys and recoveries
+    #[returns(SolvencyResponse)]
+    GetSolvency { denom: String },
+    #[returns(Vec<Route>)]
+    ListRoutes {},
+    #[returns(Vec<SignaturePolicy>)]
+    ListSignaturePolicies {},
+    /// Every signing domain version and whether proofs in it are accepted
+    #[returns(Vec<SigningDomainResponse>)]
+    ListSigningDomains {},
     #[returns(bool)]
     GetHasClaimedReward {
         survey_id: String,
         participant: String,
     },
+    /// Participants already rewarded from `survey_id`, ordered by address
+    #[returns(Vec<String>)]
+    ListRewardedParticipants {
+        survey_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Surveys `participant` has been rewarded from, ordered by survey id
+    #[returns(Vec<ParticipantRewardResponse>)]
+    ListParticipantRewards {
+        participant: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<crate::state::ibc::IBCTransfer>)]
+    GetRecoveries { addr: String },
 }
 
 #[cw_serde]
@@ -135,77 +266,16 @@ pub enum IBCLifecycleComplete {
     },
 }
 
+/// Protobuf response of `MsgTransfer`, returned in the transfer submessage reply
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgTransferResponse {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+}
+
 /// Message type for `sudo` entry_point
 #[cw_serde]
 pub enum SudoMsg {
     #[serde(rename = "ibc_lifecycle_complete")]
     IBCLifecycleComplete(IBCLifecycleComplete),
 }
-
-#[cw_serde]
-pub struct CreateSurveyResponse {
-    pub survey_id: String,
-    pub participants_limit: u32,
-    pub reward_amount: u128,
-    pub reward_denom: String,
-    pub timestamp: u64,
-}
-
-impl CreateSurveyResponse {
-    pub fn new(
-        survey_id: &str,
-        participants_limit: u32,
-        reward_amount: u128,
-        reward_denom: &str,
-        timestamp: u64,
-    ) -> Self {
-        Self {
-            survey_id: survey_id.to_string(),
-            participants_limit,
-            reward_amount,
-            reward_denom: reward_denom.to_string(),
-            timestamp,
-        }
-    }
-}
-
-#[cw_serde]
-pub struct CancelSurveyResponse {
-    pub survey_id: String,
-    pub amount_refunded: u128,
-    pub timestamp: u64,
-}
-
-impl CancelSurveyResponse {
-    pub fn new(survey_id: &str, amount: u128, timestamp: u64) -> Self {
-        Self {
-            survey_id: survey_id.to_string(),
-            amount_refunded: amount,
-            timestamp,
-        }
-    }
-}
-
-#[cw_serde]
-pub struct PayRewardsResponse {
-    pub survey_ids: Vec<String>,
-    pub participants: Vec<String>,
-    pub total_rewards_paid: u128,
-    pub timestamp: u64,
-}
-
-impl PayRewardsResponse {
-    pub fn new(
-        survey_ids: Vec<String>,
-        rewards: u128,
-        participants: Vec<String>,
-        timestamp: u64,
-    ) -> Self {
-        Self {
-            survey_ids,
-            total_rewards_paid: rewards,
-            participants,
-            timestamp,
-        }
-    }
-}
@@ -1,77 +1,11 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Binary};
-use cw_storage_plus::{Item, Map};
-
-#[cw_serde]
-pub struct ManagerInfo {
-    pub address: Addr,
-    pub pub_key: Binary,
-    pub status: bool,
-}
-
-#[cw_serde]
-pub struct Config {
-    pub owner: Addr,
-    pub receiver_prefix: String,
-    pub channel_id: String,
-}
-
-pub const CONFIG: Item<Config> = Item::new("config");
-
-// Managers
-pub const MANAGERS: Map<&Addr, ManagerInfo> = Map::new("managers");
-
-#[cw_serde]
-pub struct SurveyInfo {
-    pub survey_creator: Addr,
-    pub participants_limit: u32,
-    pub reward_amount: u128,
-    pub participants_rewarded: u32,
-    pub survey_hash: String,
-    pub is_cancelled: bool,
-    pub reward_denom: String,
-}
-
-pub const SURVEYS: Map<&str, SurveyInfo> = Map::new("surveys");
-
-// Survey rewarded users
-pub const SURVEY_REWARDED_USERS: Map<(&str, &Addr), bool> = Map::new("survey_rewarded_users");
-
-// Used proof tokens
-pub const USED_PROOF_TOKENS: Map<&String, bool> = Map::new("used_proof_tokens");
-
-#[cw_serde]
-pub struct CreateSurveyPayload<'a> {
-    pub token: &'a str,
-    pub time_to_expire: u64,
-    pub owner: &'a str,
-    pub survey_id: &'a str,
-    pub participants_limit: u32,
-    pub reward_amount: u128,
-    pub survey_hash: String,
-    pub reward_denom: &'a str,
-    pub domain: &'a str,
-}
-
-#[cw_serde]
-pub struct CancelSurveyPayload<'a> {
-    pub token: &'a str,
-    pub time_to_expire: u64,
-    pub survey_id: &'a str,
-    pub domain: &'a str,
-}
-
-#[cw_serde]
-pub struct PayRewardsPayload<'a> {
-    pub token: &'a str,
-    pub time_to_expire: u64,
-    pub survey_id: Vec<String>,
-    pub participants: Vec<String>,
-    pub domain: &'a str,
-}
+use cosmwasm_std::{Addr, Coin};
+use cw_storage_plus::Map;
+use quizzler_core::transfer::Transfer;
 
 pub mod ibc {
     use super::*;
+    use quizzler_core::transfer::TransferKind;
 
     #[cw_serde]
     pub enum PacketLifecycleStatus {
@@ -86,6 +20,8 @@ pub mod ibc {
     #[cw_serde]
     pub struct IBCTransfer {
         pub recovery_addr: Addr,
+        pub survey_id: String,
+        pub kind: TransferKind,
         pub channel_id: String,
         pub sequence: u64,
         pub amount: u128,
@@ -94,6 +30,21 @@ pub mod ibc {
     }
 }
 
+impl From<ibc::IBCTransfer> for Transfer {
+    fn from(packet: ibc::IBCTransfer) -> Self {
+        Transfer {
+            kind: packet.kind,
+            survey_id: packet.survey_id,
+            recipient: packet.recovery_addr,
+            coin: Coin::new(packet.amount, packet.denom),
+        }
+    }
+}
+
+/// Reply id used for every outgoing transfer submessage. The packet being tracked travels
+/// in the submessage payload, so a single id is enough.
+pub const IBC_TRANSFER_REPLY_ID: u64 = 1;
+
 /// In-Flight packets by (source_channel_id, sequence)
 pub const INFLIGHT_PACKETS: Map<(&str, u64), ibc::IBCTransfer> = Map::new("inflight");
 
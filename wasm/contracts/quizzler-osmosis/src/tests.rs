@@ -0,0 +1,2011 @@
+use crate::contract::{execute, instantiate, migrate, query, reply, sudo};
+use crate::helpers::transfer_memo;
+use crate::msg::{
+    ExecuteMsg, IBCLifecycleComplete, InstantiateMsg, Manager, ManagerSignature, MigrateMsg,
+    MsgTransferResponse, OwnershipResponse, ParticipantRewardResponse, QueryMsg, Route,
+    SignaturePolicy, SolvencyResponse, SudoMsg, SurveyResponse,
+};
+use crate::state::ibc::{IBCTransfer, PacketLifecycleStatus};
+use crate::state::INFLIGHT_PACKETS;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+use cosmwasm_std::{
+    coins, to_json_string, to_json_vec, Addr, Api, BankMsg, Binary, BlockInfo, Coin, CustomMsg,
+    CustomQuery, Empty, Event, IbcMsg, IbcQuery, MsgResponse, Order, Querier, Storage, Timestamp,
+};
+use cw_multi_test::error::{bail, AnyResult};
+use cw_multi_test::{
+    App, AppBuilder, AppResponse, BankKeeper, BankSudo, ContractWrapper, CosmosRouter,
+    DistributionKeeper, Executor, FailingModule, Ibc, IntoBech32, Module, StakeKeeper, WasmKeeper,
+};
+use cw_storage_plus::{Item, Map};
+use ed25519_dalek::{Signer, SigningKey};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use prost::Message;
+use quizzler_core::forward::{forwarding, PacketMemo};
+use quizzler_core::msg::ForwardHop;
+use quizzler_core::state::{Config, DomainVersion, KeyType, PROOF_TOKEN_BUCKET_SECONDS};
+use quizzler_core::ContractError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+const DENOM: &str = "uosmo";
+const CHANNEL: &str = "channel-0";
+const REWARD: u128 = 100;
+
+type TestApp = App<
+    BankKeeper,
+    MockApi,
+    MockStorage,
+    FailingModule<Empty, Empty, Empty>,
+    WasmKeeper<Empty, Empty>,
+    StakeKeeper,
+    DistributionKeeper,
+    IbcTransferModule,
+>;
+
+/// Stands in for the transfer module. The sent funds are burned as if escrowed, and the
+/// reply carries the packet sequence like a real `MsgTransfer`.
+struct IbcTransferModule;
+
+const NEXT_SEQUENCE: Item<u64> = Item::new("next_sequence");
+
+impl Module for IbcTransferModule {
+    type ExecT = IbcMsg;
+    type QueryT = IbcQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: IbcMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let IbcMsg::Transfer {
+            channel_id,
+            to_address,
+            amount,
+            memo,
+            ..
+        } = msg
+        else {
+            bail!("unsupported ibc message");
+        };
+
+        router.execute(
+            api,
+            storage,
+            block,
+            sender,
+            BankMsg::Burn {
+                amount: vec![amount],
+            }
+            .into(),
+        )?;
+
+        let sequence = NEXT_SEQUENCE.may_load(storage)?.unwrap_or(1);
+        NEXT_SEQUENCE.save(storage, &(sequence + 1))?;
+
+        Ok(AppResponse {
+            events: vec![Event::new("transfer")
+                .add_attribute("sequence", sequence.to_string())
+                .add_attribute("channel", channel_id)
+                .add_attribute("to_address", to_address)
+                .add_attribute("memo", memo.unwrap_or_default())],
+            msg_responses: vec![MsgResponse {
+                type_url: "/ibc.applications.transfer.v1.MsgTransferResponse".to_string(),
+                value: MsgTransferResponse { sequence }.encode_to_vec().into(),
+            }],
+            ..Default::default()
+        })
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        _storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        _request: IbcQuery,
+    ) -> AnyResult<Binary> {
+        bail!("unsupported ibc query")
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        _msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("unsupported ibc sudo")
+    }
+}
+
+impl Ibc for IbcTransferModule {}
+
+fn attribute(response: &AppResponse, key: &str) -> Option<String> {
+    response
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attribute| attribute.key == key)
+        .map(|attribute| attribute.value.clone())
+}
+
+struct Suite {
+    app: TestApp,
+    code_id: u64,
+    contract: Addr,
+    owner: Addr,
+    funder: Addr,
+    keys: Vec<SigningKey>,
+}
+
+fn setup(managers: u8) -> Suite {
+    let api = MockApi::default();
+    let owner = api.addr_make("owner");
+    let funder = api.addr_make("funder");
+
+    let mut app = AppBuilder::new()
+        .with_ibc(IbcTransferModule)
+        .build(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &funder, coins(10_000, DENOM))
+                .unwrap();
+        });
+
+    let code_id = app.store_code(Box::new(
+        ContractWrapper::new(execute, instantiate, query)
+            .with_sudo(sudo)
+            .with_reply(reply)
+            .with_migrate(migrate),
+    ));
+
+    let keys: Vec<SigningKey> = (1..=managers)
+        .map(|seed| SigningKey::from_bytes(&[seed; 32]))
+        .collect();
+    let managers = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| Manager {
+            addr: api.addr_make(&format!("manager{index}")).to_string(),
+            pub_key: Binary::from(key.verifying_key().to_bytes()).to_base64(),
+            key_type: KeyType::Ed25519,
+        })
+        .collect();
+
+    let contract = app
+        .instantiate_contract(
+            code_id,
+            owner.clone(),
+            &InstantiateMsg {
+                managers,
+                routes: vec![Route {
+                    prefix: "agoric".to_string(),
+                    channel_id: CHANNEL.to_string(),
+                    forward: vec![],
+                }],
+                ibc_timeout_seconds: None,
+                ibc_timeout_block: None,
+            },
+            &[],
+            "quizzler",
+            Some(owner.to_string()),
+        )
+        .unwrap();
+
+    Suite {
+        app,
+        code_id,
+        contract,
+        owner,
+        funder,
+        keys,
+    }
+}
+
+impl Suite {
+    fn now(&self) -> u64 {
+        self.app.block_info().time.seconds()
+    }
+
+    fn set_time(&mut self, seconds: u64) {
+        self.app
+            .update_block(|block| block.time = Timestamp::from_seconds(seconds));
+    }
+
+    fn query<T: DeserializeOwned>(&self, msg: &QueryMsg) -> T {
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.contract, msg)
+            .unwrap()
+    }
+
+    fn sign(&self, proof: &Binary, signers: &[usize]) -> Vec<ManagerSignature> {
+        signers
+            .iter()
+            .map(|&index| {
+                let key = &self.keys[index];
+                ManagerSignature {
+                    pub_key: Binary::from(key.verifying_key().to_bytes()).to_base64(),
+                    signature: Binary::from(key.sign(proof.as_slice()).to_bytes()).to_base64(),
+                }
+            })
+            .collect()
+    }
+
+    fn create_survey(
+        &mut self,
+        survey_id: &str,
+        owner: &str,
+        participants_limit: u32,
+        token: &str,
+        time_to_expire: u64,
+        signers: &[usize],
+    ) -> AnyResult<AppResponse> {
+        self.create_survey_ending(
+            survey_id,
+            owner,
+            participants_limit,
+            token,
+            time_to_expire,
+            signers,
+            None,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_survey_ending(
+        &mut self,
+        survey_id: &str,
+        owner: &str,
+        participants_limit: u32,
+        token: &str,
+        time_to_expire: u64,
+        signers: &[usize],
+        ends_at: Option<u64>,
+    ) -> AnyResult<AppResponse> {
+        let proof: Binary = self.query(&QueryMsg::CreateSurveyProof {
+            token: token.to_string(),
+            time_to_expire,
+            owner: owner.to_string(),
+            survey_id: survey_id.to_string(),
+            participants_limit,
+            reward_amount: REWARD,
+            survey_hash: "hash".to_string(),
+            reward_denom: DENOM.to_string(),
+            ends_at,
+            domain_version: None,
+        });
+
+        self.app.execute_contract(
+            self.funder.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::CreateSurvey {
+                signatures: self.sign(&proof, signers),
+                token: token.to_string(),
+                time_to_expire,
+                owner: owner.to_string(),
+                survey_id: survey_id.to_string(),
+                participants_limit,
+                reward_denom: DENOM.to_string(),
+                reward_amount: REWARD,
+                survey_hash: "hash".to_string(),
+                ends_at,
+                domain_version: None,
+            },
+            &coins(participants_limit as u128 * REWARD, DENOM),
+        )
+    }
+
+    fn fund_survey(&mut self, survey_id: &str, additional_participants: u32, token: &str) {
+        let funds = coins(additional_participants as u128 * REWARD, DENOM);
+        self.fund_survey_with(survey_id, additional_participants, token, &funds)
+            .unwrap();
+    }
+
+    fn fund_survey_with(
+        &mut self,
+        survey_id: &str,
+        additional_participants: u32,
+        token: &str,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        let time_to_expire = self.now() + 600;
+        let proof: Binary = self.query(&QueryMsg::FundSurveyProof {
+            token: token.to_string(),
+            time_to_expire,
+            survey_id: survey_id.to_string(),
+            additional_participants,
+            domain_version: None,
+        });
+
+        self.app.execute_contract(
+            self.funder.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::FundSurvey {
+                signatures: self.sign(&proof, &[0]),
+                token: token.to_string(),
+                time_to_expire,
+                survey_id: survey_id.to_string(),
+                additional_participants,
+                domain_version: None,
+            },
+            funds,
+        )
+    }
+
+    fn pay_rewards(
+        &mut self,
+        survey_ids: &[&str],
+        participants: &[&str],
+        token: &str,
+    ) -> AppResponse {
+        let survey_ids: Vec<String> = survey_ids.iter().map(|id| id.to_string()).collect();
+        let participants: Vec<String> = participants.iter().map(|p| p.to_string()).collect();
+        let time_to_expire = self.now() + 600;
+        let proof: Binary = self.query(&QueryMsg::PayRewardsProof {
+            token: token.to_string(),
+            time_to_expire,
+            survey_ids: survey_ids.clone(),
+            participants: participants.clone(),
+            domain_version: None,
+        });
+
+        self.app
+            .execute_contract(
+                self.owner.clone(),
+                self.contract.clone(),
+                &ExecuteMsg::PayRewards {
+                    signatures: self.sign(&proof, &[0]),
+                    token: token.to_string(),
+                    time_to_expire,
+                    survey_ids,
+                    participants,
+                    domain_version: None,
+                },
+                &[],
+            )
+            .unwrap()
+    }
+
+    fn cancel_survey(&mut self, survey_id: &str, token: &str) {
+        self.cancel_survey_in(survey_id, token, None).unwrap();
+    }
+
+    // Cancels with a proof signed in the `domain_version` schema
+    fn cancel_survey_in(
+        &mut self,
+        survey_id: &str,
+        token: &str,
+        domain_version: Option<DomainVersion>,
+    ) -> AnyResult<AppResponse> {
+        let time_to_expire = self.now() + 600;
+        let proof: Binary = self.query(&QueryMsg::CancelSurveyProof {
+            token: token.to_string(),
+            time_to_expire,
+            survey_id: survey_id.to_string(),
+            domain_version: domain_version.clone(),
+        });
+
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::CancelSurvey {
+                signatures: self.sign(&proof, &[0]),
+                token: token.to_string(),
+                time_to_expire,
+                survey_id: survey_id.to_string(),
+                domain_version,
+            },
+            &[],
+        )
+    }
+
+    fn set_signing_domain(&mut self, version: DomainVersion, enabled: bool) {
+        self.app
+            .execute_contract(
+                self.owner.clone(),
+                self.contract.clone(),
+                &ExecuteMsg::SetSigningDomain { version, enabled },
+                &[],
+            )
+            .unwrap();
+    }
+
+    // The transfer module refunds the escrowed funds before reporting the error ack
+    fn fail_packet(&mut self, sequence: u64, amount: u128) {
+        self.app
+            .sudo(
+                BankSudo::Mint {
+                    to_address: self.contract.to_string(),
+                    amount: coins(amount, DENOM),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        self.app
+            .wasm_sudo(
+                self.contract.clone(),
+                &SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCAck {
+                    channel: CHANNEL.to_string(),
+                    sequence,
+                    ack: r#"{"error":"failed"}"#.to_string(),
+                    success: false,
+                }),
+            )
+            .unwrap();
+    }
+
+    // The transfer module refunds the escrowed funds before reporting the timeout
+    fn time_out_packet(&mut self, sequence: u64, amount: u128) {
+        self.app
+            .sudo(
+                BankSudo::Mint {
+                    to_address: self.contract.to_string(),
+                    amount: coins(amount, DENOM),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        self.app
+            .wasm_sudo(
+                self.contract.clone(),
+                &SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout {
+                    channel: CHANNEL.to_string(),
+                    sequence,
+                }),
+            )
+            .unwrap();
+    }
+
+    fn inflight_packets(&self) -> Vec<IBCTransfer> {
+        let storage = self.app.contract_storage(&self.contract);
+        INFLIGHT_PACKETS
+            .range(storage.as_ref(), None, None, Order::Ascending)
+            .map(|entry| entry.unwrap().1)
+            .collect()
+    }
+
+    fn sweep_surplus(&mut self) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            self.owner.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::SweepSurplus {
+                denom: DENOM.to_string(),
+                recipient: self.owner.to_string(),
+            },
+            &[],
+        )
+    }
+
+    // Remote recovery addresses can't send the message themselves, the owner does
+    fn recover(&mut self, addr: &str) {
+        let owner = self.owner.clone();
+        self.recover_as(&owner, addr, None).unwrap();
+    }
+
+    fn recover_as(
+        &mut self,
+        sender: &Addr,
+        addr: &str,
+        receiver: Option<&str>,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::Recover {
+                addr: Some(addr.to_string()),
+                receiver: receiver.map(|receiver| receiver.to_string()),
+            },
+            &[],
+        )
+    }
+
+    fn close_expired_survey(&mut self, survey_id: &str) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            self.funder.clone(),
+            self.contract.clone(),
+            &ExecuteMsg::CloseExpiredSurvey {
+                survey_id: survey_id.to_string(),
+            },
+            &[],
+        )
+    }
+
+    fn execute_as(&mut self, sender: &Addr, msg: &ExecuteMsg) -> AnyResult<AppResponse> {
+        self.app
+            .execute_contract(sender.clone(), self.contract.clone(), msg, &[])
+    }
+
+    fn set_manager(
+        &mut self,
+        manager: &Addr,
+        pub_key: &[u8],
+        key_type: KeyType,
+        status: bool,
+    ) -> AnyResult<AppResponse> {
+        let owner = self.owner.clone();
+        self.execute_as(
+            &owner,
+            &ExecuteMsg::SetManagers {
+                managers: manager.to_string(),
+                pub_key: Binary::from(pub_key).to_base64(),
+                key_type: Some(key_type),
+                status,
+            },
+        )
+    }
+
+    fn remove_manager(&mut self, manager: &Addr) -> AnyResult<AppResponse> {
+        let owner = self.owner.clone();
+        self.execute_as(
+            &owner,
+            &ExecuteMsg::RemoveManager {
+                manager: manager.to_string(),
+            },
+        )
+    }
+
+    fn ownership(&self) -> OwnershipResponse {
+        self.query(&QueryMsg::GetOwnership {})
+    }
+
+    fn recoveries(&self, addr: &str) -> Vec<IBCTransfer> {
+        self.query(&QueryMsg::GetRecoveries {
+            addr: addr.to_string(),
+        })
+    }
+
+    fn prune_proof_tokens(&mut self) -> String {
+        let response = self
+            .app
+            .execute_contract(
+                self.owner.clone(),
+                self.contract.clone(),
+                &ExecuteMsg::PruneProofTokens { limit: None },
+                &[],
+            )
+            .unwrap();
+        attribute(&response, "pruned").unwrap()
+    }
+
+    fn is_proof_token_used(&self, token: &str) -> bool {
+        self.query(&QueryMsg::IsProofTokenUsed {
+            token: token.to_string(),
+        })
+    }
+
+    fn migrate(&mut self) {
+        self.app
+            .migrate_contract(
+                self.owner.clone(),
+                self.contract.clone(),
+                &MigrateMsg {},
+                self.code_id,
+            )
+            .unwrap();
+    }
+
+    /// Checks the contract holds what it owes, returns its (balance, liabilities)
+    fn assert_solvent(&self) -> (u128, u128) {
+        let solvency: SolvencyResponse = self.query(&QueryMsg::GetSolvency {
+            denom: DENOM.to_string(),
+        });
+        assert!(
+            solvency.balance >= solvency.liabilities,
+            "balance {} below liabilities {}",
+            solvency.balance,
+            solvency.liabilities
+        );
+        (solvency.balance, solvency.liabilities)
+    }
+}
+
+#[test]
+fn liabilities_follow_survey_lifecycle() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+    let bob = suite.app.api().addr_make("bob").to_string();
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 2, "create", time_to_expire, &[0])
+        .unwrap();
+    assert_eq!(suite.assert_solvent(), (200, 200));
+
+    suite.fund_survey("s1", 1, "fund");
+    assert_eq!(suite.assert_solvent(), (300, 300));
+
+    // Alice is paid over IBC (packet 1), bob with a bank send
+    suite.pay_rewards(&["s1", "s1"], &[&alice, &bob], "pay");
+    assert_eq!(suite.assert_solvent(), (100, 100));
+
+    // The reward comes back and is parked for alice, she still counts as rewarded
+    suite.fail_packet(1, REWARD);
+    assert_eq!(suite.assert_solvent(), (200, 200));
+    assert_eq!(suite.recoveries(&alice).len(), 1);
+    let claimed: bool = suite.query(&QueryMsg::GetHasClaimedReward {
+        survey_id: "s1".to_string(),
+        participant: alice.clone(),
+    });
+    assert!(claimed);
+
+    // Funded 300, two rewards are paid: 100 goes back to the creator in packet 2
+    suite.cancel_survey("s1", "cancel");
+    assert_eq!(suite.assert_solvent(), (100, 100));
+
+    suite.fail_packet(2, REWARD);
+    assert_eq!(suite.assert_solvent(), (200, 200));
+    assert_eq!(suite.recoveries(&creator).len(), 1);
+
+    // Only the surplus can be swept, the parked transfers stay
+    suite
+        .app
+        .send_tokens(
+            suite.funder.clone(),
+            suite.contract.clone(),
+            &coins(50, DENOM),
+        )
+        .unwrap();
+    assert_eq!(suite.assert_solvent(), (250, 200));
+    suite.sweep_surplus().unwrap();
+    assert_eq!(suite.assert_solvent(), (200, 200));
+    let err = suite.sweep_surplus().unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoSurplus {}
+    ));
+
+    suite.recover(&creator);
+    assert_eq!(suite.assert_solvent(), (100, 100));
+    assert!(suite.recoveries(&creator).is_empty());
+
+    suite.recover(&alice);
+    assert_eq!(suite.assert_solvent(), (0, 0));
+    assert!(suite.recoveries(&alice).is_empty());
+}
+
+#[test]
+fn signature_threshold_and_duplicate_signers() {
+    let mut suite = setup(3);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.contract.clone(),
+            &ExecuteMsg::SetSignaturePolicy {
+                policy: SignaturePolicy {
+                    denom: DENOM.to_string(),
+                    value_limit: REWARD,
+                    threshold: 2,
+                },
+            },
+            &[],
+        )
+        .unwrap();
+
+    // Up to the value limit a single signature is enough
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("small", &creator, 1, "small", time_to_expire, &[0])
+        .unwrap();
+
+    let err = suite
+        .create_survey("large", &creator, 2, "large", time_to_expire, &[0])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InsufficientSignatures {
+            required: 2,
+            provided: 1
+        }
+    ));
+
+    let err = suite
+        .create_survey("large", &creator, 2, "large", time_to_expire, &[0, 0])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::DuplicateSigner {}
+    ));
+
+    // The rejected attempts did not use up the token
+    suite
+        .create_survey("large", &creator, 2, "large", time_to_expire, &[0, 2])
+        .unwrap();
+    assert_eq!(suite.assert_solvent(), (300, 300));
+}
+
+/// `ManagerInfo` as stored before secp256k1 keys and the public key index
+#[cw_serde]
+struct LegacyManagerInfo {
+    address: Addr,
+    pub_key: Binary,
+    status: bool,
+}
+
+#[test]
+fn migrate_indexes_legacy_managers() {
+    let mut suite = setup(0);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let key = SigningKey::from_bytes(&[7; 32]);
+    suite.keys.push(key.clone());
+
+    let address = suite.app.api().addr_make("legacy_manager");
+    let legacy: Map<&Addr, LegacyManagerInfo> = Map::new("managers");
+    legacy
+        .save(
+            suite.app.contract_storage_mut(&suite.contract).as_mut(),
+            &address,
+            &LegacyManagerInfo {
+                address: address.clone(),
+                pub_key: Binary::from(key.verifying_key().to_bytes()),
+                status: true,
+            },
+        )
+        .unwrap();
+
+    let time_to_expire = suite.now() + 600;
+    let err = suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[0])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidSigner {}
+    ));
+
+    suite.migrate();
+    suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[0])
+        .unwrap();
+
+    // Running the migration again changes nothing
+    suite.migrate();
+    suite
+        .create_survey("s2", &creator, 1, "create2", time_to_expire, &[0])
+        .unwrap();
+}
+
+/// `SurveyInfo` as stored before survey deadlines and `funded_amount`
+#[cw_serde]
+struct LegacySurveyInfo {
+    survey_creator: Addr,
+    participants_limit: u32,
+    reward_amount: u128,
+    participants_rewarded: u32,
+    survey_hash: String,
+    is_cancelled: bool,
+    reward_denom: String,
+}
+
+impl Suite {
+    fn save_legacy_survey(&mut self, survey_id: &str, creator: &str, limit: u32, rewarded: u32) {
+        let legacy: Map<&str, LegacySurveyInfo> = Map::new("surveys");
+        legacy
+            .save(
+                self.app.contract_storage_mut(&self.contract).as_mut(),
+                survey_id,
+                &LegacySurveyInfo {
+                    survey_creator: Addr::unchecked(creator),
+                    participants_limit: limit,
+                    reward_amount: REWARD,
+                    participants_rewarded: rewarded,
+                    survey_hash: "hash".to_string(),
+                    is_cancelled: false,
+                    reward_denom: DENOM.to_string(),
+                },
+            )
+            .unwrap();
+    }
+}
+
+#[test]
+fn migrate_converts_legacy_surveys() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    suite.save_legacy_survey("legacy", &creator, 3, 1);
+
+    let survey_query = QueryMsg::GetSurvey {
+        survey_id: "legacy".to_string(),
+    };
+    assert!(suite
+        .app
+        .wrap()
+        .query_wasm_smart::<SurveyResponse>(&suite.contract, &survey_query)
+        .is_err());
+
+    suite.migrate();
+    let survey: SurveyResponse = suite.query(&survey_query);
+    assert_eq!(survey.funded_amount, 3 * REWARD);
+    assert_eq!(survey.participants_rewarded, 1);
+    assert_eq!(survey.ends_at, None);
+
+    // The converted survey is in the creator index
+    let listed: Vec<SurveyResponse> = suite.query(&QueryMsg::ListSurveys {
+        start_after: None,
+        limit: None,
+        creator: Some(creator),
+        denom: None,
+        status: None,
+    });
+    assert_eq!(listed, vec![survey.clone()]);
+
+    suite.migrate();
+    assert_eq!(suite.query::<SurveyResponse>(&survey_query), survey);
+}
+
+#[test]
+fn migrate_records_liabilities_of_legacy_surveys() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+
+    // One of three rewards was paid, the contract holds the other two
+    suite.save_legacy_survey("legacy", &creator, 3, 1);
+    suite
+        .app
+        .send_tokens(
+            suite.funder.clone(),
+            suite.contract.clone(),
+            &coins(2 * REWARD, DENOM),
+        )
+        .unwrap();
+
+    suite.migrate();
+    assert_eq!(suite.assert_solvent(), (200, 200));
+    let err = suite.sweep_surplus().unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoSurplus {}
+    ));
+
+    // Running the migration again does not count the survey twice
+    suite.migrate();
+    assert_eq!(suite.assert_solvent(), (200, 200));
+
+    // Cancelling refunds the two unpaid rewards to the creator
+    suite.cancel_survey("legacy", "cancel");
+    assert_eq!(suite.assert_solvent(), (0, 0));
+}
+
+#[test]
+fn prune_keeps_tokens_of_current_bucket() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+
+    let bucket_start = (suite.now() / PROOF_TOKEN_BUCKET_SECONDS + 1) * PROOF_TOKEN_BUCKET_SECONDS;
+    suite.set_time(bucket_start);
+
+    suite
+        .create_survey("s1", &creator, 1, "expiring", bucket_start + 60, &[0])
+        .unwrap();
+    suite
+        .create_survey(
+            "s2",
+            &creator,
+            1,
+            "later",
+            bucket_start + PROOF_TOKEN_BUCKET_SECONDS + 60,
+            &[0],
+        )
+        .unwrap();
+
+    // "expiring" has expired, but its bucket is the current one
+    suite.set_time(bucket_start + 120);
+    assert_eq!(suite.prune_proof_tokens(), "0");
+    assert!(suite.is_proof_token_used("expiring"));
+
+    suite.set_time(bucket_start + PROOF_TOKEN_BUCKET_SECONDS + 120);
+    assert_eq!(suite.prune_proof_tokens(), "1");
+    assert!(!suite.is_proof_token_used("expiring"));
+    assert!(suite.is_proof_token_used("later"));
+}
+
+#[test]
+fn migrate_gives_legacy_proof_tokens_an_expiry() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+
+    let bucket_start = (suite.now() / PROOF_TOKEN_BUCKET_SECONDS + 1) * PROOF_TOKEN_BUCKET_SECONDS;
+    let next_bucket_start = bucket_start + PROOF_TOKEN_BUCKET_SECONDS;
+    suite.set_time(bucket_start);
+    suite
+        .create_survey("s1", &creator, 1, "current", next_bucket_start + 60, &[0])
+        .unwrap();
+
+    let legacy: Map<&str, bool> = Map::new("used_proof_tokens");
+    legacy
+        .save(
+            suite.app.contract_storage_mut(&suite.contract).as_mut(),
+            "legacy",
+            &true,
+        )
+        .unwrap();
+
+    suite.migrate();
+    suite.migrate();
+    assert!(suite.is_proof_token_used("legacy"));
+
+    // The legacy token expires at the migration, the current one keeps its expiry
+    suite.set_time(next_bucket_start);
+    assert_eq!(suite.prune_proof_tokens(), "1");
+    assert!(!suite.is_proof_token_used("legacy"));
+    assert!(suite.is_proof_token_used("current"));
+}
+
+#[test]
+fn migrate_indexes_legacy_rewarded_participants() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let bob = suite.app.api().addr_make("bob");
+    suite.save_legacy_survey("legacy", &creator, 2, 1);
+
+    let legacy: Map<(&str, &Addr), bool> = Map::new("survey_rewarded_users");
+    legacy
+        .save(
+            suite.app.contract_storage_mut(&suite.contract).as_mut(),
+            ("legacy", &bob),
+            &true,
+        )
+        .unwrap();
+
+    suite.migrate();
+    suite.migrate();
+
+    let participants: Vec<String> = suite.query(&QueryMsg::ListRewardedParticipants {
+        survey_id: "legacy".to_string(),
+        start_after: None,
+        limit: None,
+    });
+    assert_eq!(participants, vec![bob.to_string()]);
+
+    let rewards: Vec<ParticipantRewardResponse> = suite.query(&QueryMsg::ListParticipantRewards {
+        participant: bob.to_string(),
+        start_after: None,
+        limit: None,
+    });
+    assert_eq!(
+        rewards,
+        vec![ParticipantRewardResponse {
+            survey_id: "legacy".to_string(),
+            reward_amount: REWARD,
+            reward_denom: DENOM.to_string(),
+        }]
+    );
+}
+
+/// `Config` as stored before routes
+#[cw_serde]
+struct LegacyConfig {
+    owner: Addr,
+    receiver_prefix: String,
+    channel_id: String,
+}
+
+#[test]
+fn migrate_turns_legacy_config_into_a_route() {
+    let mut suite = setup(1);
+    let mut storage = suite.app.contract_storage_mut(&suite.contract);
+    Item::new("config")
+        .save(
+            storage.as_mut(),
+            &LegacyConfig {
+                owner: suite.owner.clone(),
+                receiver_prefix: "agoric".to_string(),
+                channel_id: "channel-1748".to_string(),
+            },
+        )
+        .unwrap();
+    Map::<&str, Route>::new("routes").clear(storage.as_mut());
+    drop(storage);
+
+    suite.migrate();
+    suite.migrate();
+
+    let config: Config = suite.query(&QueryMsg::GetConfig {});
+    assert_eq!(
+        config,
+        Config {
+            owner: Some(suite.owner.clone()),
+            ibc_timeout_seconds: 600,
+            ibc_timeout_block: None,
+        }
+    );
+    let routes: Vec<Route> = suite.query(&QueryMsg::ListRoutes {});
+    assert_eq!(
+        routes,
+        vec![Route {
+            prefix: "agoric".to_string(),
+            channel_id: "channel-1748".to_string(),
+            forward: vec![],
+        }]
+    );
+}
+
+#[test]
+fn payouts_follow_the_recipient_route() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let bob = suite.app.api().addr_make("bob");
+    let carol = "carol".into_bech32_with_prefix("cosmos").to_string();
+
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.contract.clone(),
+            &ExecuteMsg::SetRoute {
+                route: Route {
+                    prefix: "cosmos".to_string(),
+                    channel_id: "channel-1".to_string(),
+                    forward: vec![ForwardHop {
+                        receiver: "hop".to_string(),
+                        port: None,
+                        channel: "channel-2".to_string(),
+                        timeout: None,
+                        retries: None,
+                    }],
+                },
+            },
+            &[],
+        )
+        .unwrap();
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 2, "create", time_to_expire, &[0])
+        .unwrap();
+    let response = suite.pay_rewards(&["s1", "s1"], &[bob.as_str(), &carol], "pay");
+
+    // Bob is on this chain and gets a bank send
+    let balance = suite.app.wrap().query_balance(&bob, DENOM).unwrap();
+    assert_eq!(balance.amount.u128(), REWARD);
+
+    // Carol's packet leaves on the channel of her route, addressed to its first hop
+    assert_eq!(attribute(&response, "channel").unwrap(), "channel-1");
+    assert_eq!(attribute(&response, "to_address").unwrap(), "hop");
+    assert!(attribute(&response, "memo")
+        .unwrap()
+        .contains(r#""channel":"channel-2""#));
+
+    // The packet is tracked on the same channel
+    suite
+        .app
+        .sudo(
+            BankSudo::Mint {
+                to_address: suite.contract.to_string(),
+                amount: coins(REWARD, DENOM),
+            }
+            .into(),
+        )
+        .unwrap();
+    suite
+        .app
+        .wasm_sudo(
+            suite.contract.clone(),
+            &SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout {
+                channel: "channel-1".to_string(),
+                sequence: 1,
+            }),
+        )
+        .unwrap();
+    let recoveries = suite.recoveries(&carol);
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(recoveries[0].channel_id, "channel-1");
+}
+
+fn route_with_hops(hops: usize) -> Route {
+    let forward = [
+        ForwardHop {
+            receiver: "hop1".to_string(),
+            port: None,
+            channel: "channel-2".to_string(),
+            timeout: None,
+            retries: None,
+        },
+        ForwardHop {
+            receiver: "hop2".to_string(),
+            port: Some("transfer".to_string()),
+            channel: "channel-3".to_string(),
+            timeout: Some("10m".to_string()),
+            retries: Some(2),
+        },
+    ];
+
+    Route {
+        prefix: "agoric".to_string(),
+        channel_id: CHANNEL.to_string(),
+        forward: forward[..hops].to_vec(),
+    }
+}
+
+#[test]
+fn forwarding_memo_per_hop_count() {
+    let receiver = "agoric1receiver";
+
+    assert!(forwarding(&route_with_hops(0), receiver).is_none());
+
+    let one_hop = forwarding(&route_with_hops(1), receiver).unwrap();
+    assert_eq!(one_hop.receiver, "hop1");
+    assert_eq!(
+        to_json_string(&PacketMemo {
+            forward: one_hop.forward
+        })
+        .unwrap(),
+        r#"{"forward":{"receiver":"agoric1receiver","port":"transfer","channel":"channel-2"}}"#
+    );
+
+    // Each hop forwards to the receiver of the next one, the last to the recipient
+    let two_hops = forwarding(&route_with_hops(2), receiver).unwrap();
+    assert_eq!(two_hops.receiver, "hop1");
+    assert_eq!(
+        to_json_string(&PacketMemo {
+            forward: two_hops.forward
+        })
+        .unwrap(),
+        concat!(
+            r#"{"forward":{"receiver":"hop2","port":"transfer","channel":"channel-2","#,
+            r#""next":{"forward":{"receiver":"agoric1receiver","port":"transfer","#,
+            r#""channel":"channel-3","timeout":"10m","retries":2}}}}"#
+        )
+    );
+}
+
+#[test]
+fn transfer_memo_combines_forwarding_and_ibc_callback() {
+    let env = mock_env();
+    let contract = env.contract.address.to_string();
+    let receiver = "agoric1receiver";
+    let memo = |hops: usize| {
+        let forward = forwarding(&route_with_hops(hops), receiver).map(|f| f.forward);
+        transfer_memo(&env, forward).unwrap()
+    };
+
+    assert_eq!(memo(0), format!(r#"{{"ibc_callback":"{contract}"}}"#));
+    assert_eq!(
+        memo(1),
+        format!(
+            concat!(
+                r#"{{"forward":{{"receiver":"agoric1receiver","port":"transfer","#,
+                r#""channel":"channel-2"}},"ibc_callback":"{}"}}"#
+            ),
+            contract
+        )
+    );
+    assert_eq!(
+        memo(2),
+        format!(
+            concat!(
+                r#"{{"forward":{{"receiver":"hop2","port":"transfer","channel":"channel-2","#,
+                r#""next":{{"forward":{{"receiver":"agoric1receiver","port":"transfer","#,
+                r#""channel":"channel-3","timeout":"10m","retries":2}}}}}},"#,
+                r#""ibc_callback":"{}"}}"#
+            ),
+            contract
+        )
+    );
+}
+
+#[test]
+fn only_the_recovery_address_or_owner_redirects_recoveries() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+    let dave = "dave".into_bech32_with_prefix("agoric").to_string();
+    let bob = suite.app.api().addr_make("bob");
+    let manager = suite.app.api().addr_make("manager0");
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[0])
+        .unwrap();
+    suite.pay_rewards(&["s1"], &[&alice], "pay");
+    suite.fail_packet(1, REWARD);
+
+    // A manager can re-send alice's funds to her but not send them elsewhere
+    let err = suite.recover_as(&manager, &alice, Some(&dave)).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    ));
+    let response = suite.recover_as(&manager, &alice, None).unwrap();
+    assert_eq!(attribute(&response, "to_address").unwrap(), alice);
+    assert!(suite.recoveries(&alice).is_empty());
+
+    // Alice herself picks another receiver, where the funds are parked if they fail again
+    suite.fail_packet(2, REWARD);
+    let response = suite
+        .recover_as(&Addr::unchecked(&alice), &alice, Some(&dave))
+        .unwrap();
+    assert_eq!(attribute(&response, "to_address").unwrap(), dave);
+    suite.fail_packet(3, REWARD);
+    assert!(suite.recoveries(&alice).is_empty());
+    assert_eq!(suite.recoveries(&dave).len(), 1);
+
+    // The owner redirects for accounts that can't send the message
+    let owner = suite.owner.clone();
+    suite.recover_as(&owner, &dave, Some(bob.as_str())).unwrap();
+    assert!(suite.recoveries(&dave).is_empty());
+    let balance = suite.app.wrap().query_balance(&bob, DENOM).unwrap();
+    assert_eq!(balance.amount.u128(), REWARD);
+    assert_eq!(suite.assert_solvent(), (0, 0));
+}
+
+#[test]
+fn expired_survey_refunds_its_creator() {
+    let mut suite = setup(1);
+    let creator = suite.app.api().addr_make("creator");
+    let bob = suite.app.api().addr_make("bob").to_string();
+
+    let ends_at = suite.now() + 1_000;
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey_ending(
+            "s1",
+            creator.as_str(),
+            3,
+            "create",
+            time_to_expire,
+            &[0],
+            Some(ends_at),
+        )
+        .unwrap();
+    suite.pay_rewards(&["s1"], &[&bob], "pay");
+    assert_eq!(suite.assert_solvent(), (200, 200));
+
+    // Still open until the block time reaches `ends_at`
+    suite.set_time(ends_at - 1);
+    let err = suite.close_expired_survey("s1").unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::SurveyNotExpired {}
+    ));
+
+    // Funded 300, one reward is paid: the other 200 go back to the creator
+    suite.set_time(ends_at);
+    let response = suite.close_expired_survey("s1").unwrap();
+    assert_eq!(attribute(&response, "amount").unwrap(), "200");
+    let balance = suite.app.wrap().query_balance(&creator, DENOM).unwrap();
+    assert_eq!(balance.amount.u128(), 200);
+    assert_eq!(suite.assert_solvent(), (0, 0));
+
+    let survey: SurveyResponse = suite.query(&QueryMsg::GetSurvey {
+        survey_id: "s1".to_string(),
+    });
+    assert!(survey.is_cancelled);
+
+    let err = suite.close_expired_survey("s1").unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::SurveyAlreadyCancelled {}
+    ));
+}
+
+#[test]
+fn survey_without_deadline_never_expires() {
+    let mut suite = setup(1);
+    let creator = suite.app.api().addr_make("creator").to_string();
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[0])
+        .unwrap();
+
+    suite.set_time(suite.now() + 10 * 365 * 24 * 3600);
+    let err = suite.close_expired_survey("s1").unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::SurveyNotExpired {}
+    ));
+    assert_eq!(suite.assert_solvent(), (100, 100));
+}
+
+#[test]
+fn retired_signing_domain_rejects_its_proofs() {
+    let mut suite = setup(1);
+    let creator = suite.app.api().addr_make("creator").to_string();
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[0])
+        .unwrap();
+
+    suite.set_signing_domain(DomainVersion::V2, true);
+    suite.set_signing_domain(DomainVersion::V1, false);
+
+    let err = suite
+        .cancel_survey_in("s1", "cancel", Some(DomainVersion::V1))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::SigningDomainNotEnabled { version } if version == "SURVEY_V1"
+    ));
+    // Without a version the proof is read as V1 too
+    let err = suite.cancel_survey_in("s1", "cancel", None).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::SigningDomainNotEnabled { .. }
+    ));
+
+    suite
+        .cancel_survey_in("s1", "cancel", Some(DomainVersion::V2))
+        .unwrap();
+    let survey: SurveyResponse = suite.query(&QueryMsg::GetSurvey {
+        survey_id: "s1".to_string(),
+    });
+    assert!(survey.is_cancelled);
+}
+
+#[derive(Serialize)]
+struct Domain<'a> {
+    name: &'a str,
+    chain_id: &'a str,
+    contract: &'a str,
+}
+
+#[derive(Serialize)]
+struct CancelPayload<'a> {
+    token: &'a str,
+    time_to_expire: u64,
+    survey_id: &'a str,
+    domain: Domain<'a>,
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    action: &'a str,
+    payload: &'a CancelPayload<'a>,
+}
+
+fn sha256<T: Serialize>(value: &T) -> Binary {
+    Binary::from(Sha256::digest(to_json_vec(value).unwrap()).to_vec())
+}
+
+#[test]
+fn v2_proof_envelope_names_the_action() {
+    let suite = setup(1);
+    let chain_id = suite.app.block_info().chain_id;
+    let time_to_expire = suite.now() + 600;
+    let proof = |version: DomainVersion| -> Binary {
+        suite.query(&QueryMsg::CancelSurveyProof {
+            token: "cancel".to_string(),
+            time_to_expire,
+            survey_id: "s1".to_string(),
+            domain_version: Some(version),
+        })
+    };
+    let payload = |name| CancelPayload {
+        token: "cancel",
+        time_to_expire,
+        survey_id: "s1",
+        domain: Domain {
+            name,
+            chain_id: &chain_id,
+            contract: suite.contract.as_str(),
+        },
+    };
+
+    // V1 signs the bare payload
+    assert_eq!(proof(DomainVersion::V1), sha256(&payload("SURVEY_V1")));
+
+    // V2 signs the payload wrapped with the action, so the same payload under another
+    // action hashes differently
+    let v2_payload = payload("SURVEY_V2");
+    let v2_proof = proof(DomainVersion::V2);
+    assert_eq!(
+        v2_proof,
+        sha256(&Envelope {
+            action: "cancel_survey",
+            payload: &v2_payload,
+        })
+    );
+    assert_ne!(v2_proof, sha256(&v2_payload));
+    assert_ne!(
+        v2_proof,
+        sha256(&Envelope {
+            action: "fund_survey",
+            payload: &v2_payload,
+        })
+    );
+}
+
+#[test]
+fn ownership_moves_only_when_accepted_in_time() {
+    let mut suite = setup(1);
+    let owner = suite.owner.clone();
+    let alice = suite.app.api().addr_make("alice");
+    let bob = suite.app.api().addr_make("bob");
+
+    let expiry = suite.now() + 100;
+    suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::ProposeOwner {
+                new_owner: alice.to_string(),
+                expiry: Some(expiry),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        suite.ownership(),
+        OwnershipResponse {
+            owner: Some(owner.to_string()),
+            pending_owner: Some(alice.to_string()),
+            pending_expiry: Some(expiry),
+        }
+    );
+
+    // Only the proposed owner can accept
+    let err = suite
+        .execute_as(&bob, &ExecuteMsg::AcceptOwnership {})
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    ));
+
+    // Not once the proposal has expired
+    suite.set_time(expiry);
+    let err = suite
+        .execute_as(&alice, &ExecuteMsg::AcceptOwnership {})
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::OwnershipProposalExpired {}
+    ));
+    assert_eq!(suite.ownership().owner, Some(owner.to_string()));
+
+    suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::ProposeOwner {
+                new_owner: alice.to_string(),
+                expiry: None,
+            },
+        )
+        .unwrap();
+    suite
+        .execute_as(&alice, &ExecuteMsg::AcceptOwnership {})
+        .unwrap();
+    assert_eq!(
+        suite.ownership(),
+        OwnershipResponse {
+            owner: Some(alice.to_string()),
+            pending_owner: None,
+            pending_expiry: None,
+        }
+    );
+
+    // The previous owner lost the owner only messages
+    let err = suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::ProposeOwner {
+                new_owner: owner.to_string(),
+                expiry: None,
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    ));
+}
+
+#[test]
+fn cancelled_proposal_cannot_be_accepted() {
+    let mut suite = setup(1);
+    let owner = suite.owner.clone();
+    let alice = suite.app.api().addr_make("alice");
+
+    suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::ProposeOwner {
+                new_owner: alice.to_string(),
+                expiry: None,
+            },
+        )
+        .unwrap();
+
+    // Only the owner can withdraw the proposal
+    let err = suite
+        .execute_as(&alice, &ExecuteMsg::CancelOwnershipProposal {})
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    ));
+
+    suite
+        .execute_as(&owner, &ExecuteMsg::CancelOwnershipProposal {})
+        .unwrap();
+    assert_eq!(suite.ownership().pending_owner, None);
+
+    let err = suite
+        .execute_as(&alice, &ExecuteMsg::AcceptOwnership {})
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoPendingOwner {}
+    ));
+    let err = suite
+        .execute_as(&owner, &ExecuteMsg::CancelOwnershipProposal {})
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoPendingOwner {}
+    ));
+}
+
+#[test]
+fn renounced_contract_has_no_owner() {
+    let mut suite = setup(1);
+    let owner = suite.owner.clone();
+    let alice = suite.app.api().addr_make("alice");
+
+    suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::ProposeOwner {
+                new_owner: alice.to_string(),
+                expiry: None,
+            },
+        )
+        .unwrap();
+    suite
+        .execute_as(&owner, &ExecuteMsg::RenounceOwnership {})
+        .unwrap();
+    assert_eq!(
+        suite.ownership(),
+        OwnershipResponse {
+            owner: None,
+            pending_owner: None,
+            pending_expiry: None,
+        }
+    );
+
+    // The pending proposal went with it
+    let err = suite
+        .execute_as(&alice, &ExecuteMsg::AcceptOwnership {})
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoPendingOwner {}
+    ));
+
+    // Every owner only message now fails `check_is_contract_owner`
+    for msg in [
+        ExecuteMsg::RenounceOwnership {},
+        ExecuteMsg::SetSigningDomain {
+            version: DomainVersion::V2,
+            enabled: true,
+        },
+        ExecuteMsg::SweepSurplus {
+            denom: DENOM.to_string(),
+            recipient: owner.to_string(),
+        },
+    ] {
+        let err = suite.execute_as(&owner, &msg).unwrap_err();
+        assert!(matches!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::Unauthorized {}
+        ));
+    }
+}
+
+#[test]
+fn secp256k1_manager_signs_proofs() {
+    let mut suite = setup(1);
+    let creator = suite.app.api().addr_make("creator").to_string();
+    let manager = suite.app.api().addr_make("secp_manager");
+
+    let key = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+    let pub_key = key.verifying_key().to_encoded_point(true);
+    suite
+        .set_manager(&manager, pub_key.as_bytes(), KeyType::Secp256k1, true)
+        .unwrap();
+
+    let time_to_expire = suite.now() + 600;
+    let proof: Binary = suite.query(&QueryMsg::CreateSurveyProof {
+        token: "create".to_string(),
+        time_to_expire,
+        owner: creator.clone(),
+        survey_id: "s1".to_string(),
+        participants_limit: 1,
+        reward_amount: REWARD,
+        survey_hash: "hash".to_string(),
+        reward_denom: DENOM.to_string(),
+        ends_at: None,
+        domain_version: None,
+    });
+    // The proof is the sha256 digest secp256k1 signs
+    let sign = |digest: &[u8]| -> Vec<ManagerSignature> {
+        let signature: k256::ecdsa::Signature = key.sign_prehash(digest).unwrap();
+        vec![ManagerSignature {
+            pub_key: Binary::from(pub_key.as_bytes()).to_base64(),
+            signature: Binary::from(signature.to_bytes().to_vec()).to_base64(),
+        }]
+    };
+    let create = |signatures| ExecuteMsg::CreateSurvey {
+        signatures,
+        token: "create".to_string(),
+        time_to_expire,
+        owner: creator.clone(),
+        survey_id: "s1".to_string(),
+        participants_limit: 1,
+        reward_denom: DENOM.to_string(),
+        reward_amount: REWARD,
+        survey_hash: "hash".to_string(),
+        ends_at: None,
+        domain_version: None,
+    };
+
+    let err = suite
+        .app
+        .execute_contract(
+            suite.funder.clone(),
+            suite.contract.clone(),
+            &create(sign(&[0; 32])),
+            &coins(REWARD, DENOM),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidMessageHash {}
+    ));
+
+    suite
+        .app
+        .execute_contract(
+            suite.funder.clone(),
+            suite.contract.clone(),
+            &create(sign(proof.as_slice())),
+            &coins(REWARD, DENOM),
+        )
+        .unwrap();
+    assert_eq!(suite.assert_solvent(), (REWARD, REWARD));
+}
+
+#[test]
+fn pub_key_must_match_its_key_type() {
+    let mut suite = setup(1);
+    let manager = suite.app.api().addr_make("secp_manager");
+
+    // An ed25519 sized key labelled secp256k1
+    let err = suite
+        .set_manager(&manager, &[2; 32], KeyType::Secp256k1, true)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidPubKey { key_type } if key_type == "secp256k1"
+    ));
+
+    // A compressed secp256k1 key labelled ed25519
+    let err = suite
+        .set_manager(&manager, &[2; 33], KeyType::Ed25519, true)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidPubKey { key_type } if key_type == "ed25519"
+    ));
+}
+
+fn ed25519_pub_key(key: &SigningKey) -> [u8; 32] {
+    key.verifying_key().to_bytes()
+}
+
+#[test]
+fn pub_key_belongs_to_one_manager() {
+    let mut suite = setup(2);
+    let manager0 = suite.app.api().addr_make("manager0");
+    let newcomer = suite.app.api().addr_make("newcomer");
+    let key0 = ed25519_pub_key(&suite.keys[0]);
+
+    let err = suite
+        .set_manager(&newcomer, &key0, KeyType::Ed25519, true)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::PubKeyAlreadyRegistered {}
+    ));
+
+    // The manager holding the key can still be updated with it
+    suite
+        .set_manager(&manager0, &key0, KeyType::Ed25519, false)
+        .unwrap();
+    suite
+        .set_manager(&manager0, &key0, KeyType::Ed25519, true)
+        .unwrap();
+
+    // Once the manager moves to another key, the old one is free again
+    let key2 = ed25519_pub_key(&SigningKey::from_bytes(&[9; 32]));
+    suite
+        .set_manager(&manager0, &key2, KeyType::Ed25519, true)
+        .unwrap();
+    suite
+        .set_manager(&newcomer, &key0, KeyType::Ed25519, true)
+        .unwrap();
+}
+
+#[test]
+fn removed_manager_can_no_longer_sign() {
+    let mut suite = setup(2);
+    let creator = suite.app.api().addr_make("creator").to_string();
+    let manager1 = suite.app.api().addr_make("manager1");
+    let newcomer = suite.app.api().addr_make("newcomer");
+
+    let err = suite
+        .execute_as(
+            &manager1,
+            &ExecuteMsg::RemoveManager {
+                manager: manager1.to_string(),
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    ));
+
+    suite.remove_manager(&manager1).unwrap();
+    let err = suite.remove_manager(&manager1).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidManager {}
+    ));
+
+    let time_to_expire = suite.now() + 600;
+    let err = suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[1])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidSigner {}
+    ));
+    suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[0])
+        .unwrap();
+
+    // The key left the index with its manager
+    let key1 = ed25519_pub_key(&suite.keys[1]);
+    suite
+        .set_manager(&newcomer, &key1, KeyType::Ed25519, true)
+        .unwrap();
+}
+
+#[test]
+fn signature_policy_keeps_enough_managers_active() {
+    let mut suite = setup(2);
+    let manager1 = suite.app.api().addr_make("manager1");
+    let key1 = ed25519_pub_key(&suite.keys[1]);
+    let owner = suite.owner.clone();
+
+    suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::SetSignaturePolicy {
+                policy: SignaturePolicy {
+                    denom: DENOM.to_string(),
+                    value_limit: REWARD,
+                    threshold: 2,
+                },
+            },
+        )
+        .unwrap();
+
+    // One active manager could never meet the threshold of two
+    let err = suite
+        .set_manager(&manager1, &key1, KeyType::Ed25519, false)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidConfig { .. }
+    ));
+    let err = suite.remove_manager(&manager1).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidConfig { .. }
+    ));
+
+    suite
+        .execute_as(
+            &owner,
+            &ExecuteMsg::RemoveSignaturePolicy {
+                denom: DENOM.to_string(),
+            },
+        )
+        .unwrap();
+    suite
+        .set_manager(&manager1, &key1, KeyType::Ed25519, false)
+        .unwrap();
+
+    // A disabled manager's signatures are refused like a stranger's
+    let creator = suite.app.api().addr_make("creator").to_string();
+    let time_to_expire = suite.now() + 600;
+    let err = suite
+        .create_survey("s1", &creator, 1, "create", time_to_expire, &[1])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidSigner {}
+    ));
+}
+
+#[test]
+fn failed_packets_become_recoveries() {
+    let mut suite = setup(1);
+    let creator = "creator".into_bech32_with_prefix("agoric").to_string();
+    let alice = "alice".into_bech32_with_prefix("agoric").to_string();
+    let carol = "carol".into_bech32_with_prefix("agoric").to_string();
+    let dave = "dave".into_bech32_with_prefix("agoric").to_string();
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 3, "create", time_to_expire, &[0])
+        .unwrap();
+
+    // Each reply stores the packet from its payload under the sequence the transfer got
+    let response = suite.pay_rewards(&["s1", "s1", "s1"], &[&alice, &carol, &dave], "pay");
+    assert!(response.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attribute| attribute.value == "save_inflight_packet")));
+    let inflight = suite.inflight_packets();
+    let tracked: Vec<(&str, u64, u128)> = inflight
+        .iter()
+        .map(|packet| {
+            (
+                packet.recovery_addr.as_str(),
+                packet.sequence,
+                packet.amount,
+            )
+        })
+        .collect();
+    assert_eq!(
+        tracked,
+        [
+            (alice.as_str(), 1, REWARD),
+            (carol.as_str(), 2, REWARD),
+            (dave.as_str(), 3, REWARD)
+        ]
+    );
+    assert!(inflight.iter().all(|packet| packet.channel_id == CHANNEL
+        && packet.survey_id == "s1"
+        && packet.status == PacketLifecycleStatus::Sent));
+    assert_eq!(suite.assert_solvent(), (0, 0));
+
+    // A delivered packet is dropped without a recovery
+    suite
+        .app
+        .wasm_sudo(
+            suite.contract.clone(),
+            &SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCAck {
+                channel: CHANNEL.to_string(),
+                sequence: 3,
+                ack: r#"{"result":"AQ=="}"#.to_string(),
+                success: true,
+            }),
+        )
+        .unwrap();
+    assert!(suite.recoveries(&dave).is_empty());
+
+    suite.fail_packet(1, REWARD);
+    suite.time_out_packet(2, REWARD);
+    assert!(suite.inflight_packets().is_empty());
+
+    let recoveries = suite.recoveries(&alice);
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(recoveries[0].sequence, 1);
+    assert_eq!(recoveries[0].amount, REWARD);
+    assert_eq!(recoveries[0].status, PacketLifecycleStatus::AckFailure);
+
+    let recoveries = suite.recoveries(&carol);
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(recoveries[0].sequence, 2);
+    assert_eq!(recoveries[0].status, PacketLifecycleStatus::TimedOut);
+
+    // The refunded rewards are owed to their recovery addresses
+    assert_eq!(suite.assert_solvent(), (2 * REWARD, 2 * REWARD));
+
+    // A second outcome for the same packet finds nothing to recover
+    let response = suite
+        .app
+        .wasm_sudo(
+            suite.contract.clone(),
+            &SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout {
+                channel: CHANNEL.to_string(),
+                sequence: 1,
+            }),
+        )
+        .unwrap();
+    assert_eq!(
+        attribute(&response, "msg").unwrap(),
+        "received unexpected timeout"
+    );
+    assert_eq!(suite.recoveries(&alice).len(), 1);
+}
+
+#[test]
+fn funding_must_match_the_added_participants() {
+    let mut suite = setup(1);
+    let creator = suite.app.api().addr_make("creator").to_string();
+    suite
+        .app
+        .sudo(
+            BankSudo::Mint {
+                to_address: suite.funder.to_string(),
+                amount: coins(1_000, "uatom"),
+            }
+            .into(),
+        )
+        .unwrap();
+
+    let time_to_expire = suite.now() + 600;
+    suite
+        .create_survey("s1", &creator, 2, "create", time_to_expire, &[0])
+        .unwrap();
+
+    for amount in [REWARD, 3 * REWARD] {
+        let err = suite
+            .fund_survey_with("s1", 2, "fund", &coins(amount, DENOM))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::CustomError { val } if val.starts_with("Exact funding required")
+        ));
+    }
+    let err = suite
+        .fund_survey_with("s1", 2, "fund", &coins(2 * REWARD, "uatom"))
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Payment(_)
+    ));
+    let err = suite.fund_survey_with("s1", 2, "fund", &[]).unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Payment(_)
+    ));
+
+    // Rejected attempts leave the survey and the proof token untouched
+    let survey: SurveyResponse = suite.query(&QueryMsg::GetSurvey {
+        survey_id: "s1".to_string(),
+    });
+    assert_eq!((survey.participants_limit, survey.funded_amount), (2, 200));
+
+    suite.fund_survey("s1", 2, "fund");
+    let survey: SurveyResponse = suite.query(&QueryMsg::GetSurvey {
+        survey_id: "s1".to_string(),
+    });
+    assert_eq!((survey.participants_limit, survey.funded_amount), (4, 400));
+    assert_eq!(suite.assert_solvent(), (400, 400));
+
+    // The funding is part of what the creator gets back
+    let bob = suite.app.api().addr_make("bob").to_string();
+    suite.pay_rewards(&["s1"], &[&bob], "pay");
+    suite.cancel_survey("s1", "cancel");
+    let balance = suite.app.wrap().query_balance(&creator, DENOM).unwrap();
+    assert_eq!(balance.amount.u128(), 300);
+    assert_eq!(suite.assert_solvent(), (0, 0));
+}
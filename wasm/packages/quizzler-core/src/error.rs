@@ -12,6 +12,12 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("No Pending Owner")]
+    NoPendingOwner {},
+
+    #[error("Ownership Proposal Expired")]
+    OwnershipProposalExpired {},
+
     #[error("Survey Not Found")]
     SurveyNotFound {},
 
@@ -21,9 +27,18 @@ pub enum ContractError {
     #[error("Survey Already Cancelled")]
     SurveyAlreadyCancelled {},
 
+    #[error("Survey Expired")]
+    SurveyExpired {},
+
+    #[error("Survey Not Expired")]
+    SurveyNotExpired {},
+
     #[error("Invalid Manager")]
     InvalidManager {},
 
+    #[error("Public Key Already Registered")]
+    PubKeyAlreadyRegistered {},
+
     #[error("Only Creator Or Manager")]
     OnlyCreatorOrManager {},
 
@@ -45,9 +60,18 @@ pub enum ContractError {
     #[error("Proof Expired")]
     ProofExpired {},
 
+    #[error("Proof Expiry Exceeds Signing Window")]
+    ProofWindowExceeded {},
+
+    #[error("Signing Domain Not Enabled: {version}")]
+    SigningDomainNotEnabled { version: String },
+
     #[error("Insufficient Funds")]
     InsufficientContractBalance {},
 
+    #[error("Transfer Exceeds Contract Liabilities")]
+    LiabilitiesExceeded {},
+
     #[error("Survey Creation Failed")]
     SurveyCreationFailed {},
 
@@ -60,6 +84,15 @@ pub enum ContractError {
     #[error("Invalid Signer")]
     InvalidSigner {},
 
+    #[error("Duplicate Signer")]
+    DuplicateSigner {},
+
+    #[error("Insufficient Signatures: {provided} of {required}")]
+    InsufficientSignatures { required: u32, provided: u32 },
+
+    #[error("Invalid {key_type} Public Key")]
+    InvalidPubKey { key_type: String },
+
     #[error("Invalid Reward Amount")]
     InvalidRewardAmount {},
 
@@ -72,6 +105,9 @@ pub enum ContractError {
     #[error("Invalid Address")]
     InvalidAddress {},
 
+    #[error("Invalid Config: {reason}")]
+    InvalidConfig { reason: String },
+
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
 
@@ -84,8 +120,8 @@ pub enum ContractError {
     #[error("Invalid Account: {receiver}")]
     InvalidAccount { receiver: String },
 
-    #[error("Expected Agoric Account: {receiver}")]
-    ExpectedAgoricAccount { receiver: String },
+    #[error("No Route For Account: {receiver}")]
+    NoRoute { receiver: String },
 
     #[error("Sequence not found in request packet")]
     SequenceNotFound {},
@@ -108,13 +144,28 @@ pub enum ContractError {
     #[error("No data in reply")]
     NoDataInReply {},
 
-    #[error("Nothing to refund")]
+    #[error("Nothing to Refund")]
     NothingToRefund {},
 
+    #[error("Insufficient Fee Reserve: {denom}")]
+    InsufficientFeeReserve { denom: String },
+
+    #[error("No Surplus")]
+    NoSurplus {},
+
+    #[error("Nothing to Recover")]
+    NothingToRecover {},
+
     #[error("Failed to parse MsgSubmitTxResponse: {error}")]
     ResponseParseFailed { error: String },
 }
 
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
+}
+
 impl From<cosmwasm_std::VerificationError> for ContractError {
     fn from(err: cosmwasm_std::VerificationError) -> Self {
         Self::SignatureVerificationFailed(err.to_string())
@@ -0,0 +1,1077 @@
+use crate::error::ContractError;
+use crate::helpers;
+use crate::msg::{
+    CancelSurveyResponse, CreateSurveyResponse, FundSurveyResponse, ManagerSignature,
+    PayRewardsResponse, Route, SignaturePolicy,
+};
+use crate::query;
+use crate::state::{
+    managers, proof_token_bucket, survey_rewarded_users, surveys, Config, DomainVersion, KeyType,
+    ManagerInfo, PendingOwner, RewardedUser, SurveyInfo, CONFIG, LIABILITIES, PENDING_OWNER,
+    PROOF_TOKEN_EXPIRIES, ROUTES, SIGNATURE_POLICIES, SIGNING_DOMAINS, USED_PROOF_TOKENS,
+};
+use crate::transfer::{self, Transfer, TransferAdapter, TransferKind};
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Binary, Coin, DepsMut, Env, Event, IbcTimeoutBlock, MessageInfo,
+    Order, Response, StdResult, Storage, SubMsg, Uint128,
+};
+use cw_storage_plus::{Bound, Map};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Bounds on the used proof tokens deleted by one prune
+const DEFAULT_PRUNE_LIMIT: u32 = 50;
+const MAX_PRUNE_LIMIT: u32 = 200;
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_survey(
+    ctx: (DepsMut, &Env, MessageInfo),
+    signatures: Vec<ManagerSignature>,
+    token: String,
+    time_to_expire: u64,
+    owner: String,
+    survey_id: String,
+    participants_limit: u32,
+    reward_denom: String,
+    reward_amount: u128,
+    survey_hash: String,
+    ends_at: Option<u64>,
+    domain_version: DomainVersion,
+) -> Result<Response, ContractError> {
+    let (mut deps, env, info) = ctx;
+
+    if surveys().has(deps.storage, &survey_id) {
+        return Err(ContractError::SurveyAlreadyExists {});
+    }
+
+    if reward_amount == 0 {
+        return Err(ContractError::InvalidRewardAmount {});
+    }
+
+    if participants_limit == 0 {
+        return Err(ContractError::CustomError {
+            val: "Participants limit must be greater than 0".to_string(),
+        });
+    }
+
+    if ends_at.is_some_and(|ends_at| ends_at <= env.block.time.seconds()) {
+        return Err(ContractError::CustomError {
+            val: "Survey end time must be in the future".to_string(),
+        });
+    }
+
+    helpers::check_domain_enabled(deps.storage, &domain_version)?;
+
+    let message_hash = query::create_survey_proof(
+        env,
+        &domain_version,
+        &token,
+        time_to_expire,
+        &owner,
+        &survey_id,
+        participants_limit,
+        reward_amount,
+        &survey_hash,
+        &reward_denom,
+        ends_at,
+    )?;
+
+    let amount_to_survey = (participants_limit as u128)
+        .checked_mul(reward_amount)
+        .ok_or(ContractError::ArithmeticError {})?;
+
+    let required_signatures =
+        helpers::required_signatures(deps.storage, &reward_denom, amount_to_survey)?;
+
+    helpers::auth_validations(
+        &mut deps,
+        env,
+        token.clone(),
+        message_hash,
+        &signatures,
+        time_to_expire,
+        required_signatures,
+    )?;
+
+    let (_, validated_owner_addr) = helpers::validate_account(deps.as_ref(), &owner)?;
+
+    let amount_sent = cw_utils::must_pay(&info, &reward_denom)?;
+
+    // Ensure exact funding - prevent both underfunding and overfunding
+    if amount_sent != Uint128::from(amount_to_survey) {
+        return Err(ContractError::CustomError {
+            val: format!(
+                "Exact funding required: expected {} {}, got {}",
+                amount_to_survey, reward_denom, amount_sent
+            ),
+        });
+    }
+
+    // Save survey info
+    let survey_info = SurveyInfo {
+        survey_creator: validated_owner_addr,
+        participants_limit,
+        reward_denom: reward_denom.clone(),
+        reward_amount,
+        participants_rewarded: 0,
+        survey_hash,
+        is_cancelled: false,
+        ends_at,
+        funded_amount: amount_to_survey,
+    };
+
+    surveys().save(deps.storage, &survey_id, &survey_info)?;
+    transfer::record_liability(deps.storage, &reward_denom, amount_to_survey)?;
+
+    let response_data = CreateSurveyResponse::new(
+        &survey_id,
+        participants_limit,
+        reward_amount,
+        &reward_denom,
+        env.block.time.seconds(),
+    );
+
+    Ok(Response::new()
+        .set_data(to_json_binary(&response_data)?)
+        .add_attribute("action", "create_survey")
+        .add_attribute("domain_version", domain_version.as_str())
+        .add_attribute("survey_id", survey_id)
+        .add_attribute("owner", owner)
+        .add_attribute("reward_denom", reward_denom)
+        .add_attribute("reward_amount", reward_amount.to_string())
+        .add_attribute("participants_limit", participants_limit.to_string())
+        .add_attribute("amount_funded", amount_sent.to_string()))
+}
+
+/// Raises the participants limit of an open survey, the sender pays exactly the
+/// rewards of the additional participants
+#[allow(clippy::too_many_arguments)]
+pub fn fund_survey(
+    ctx: (DepsMut, &Env, MessageInfo),
+    signatures: Vec<ManagerSignature>,
+    token: String,
+    time_to_expire: u64,
+    survey_id: String,
+    additional_participants: u32,
+    domain_version: DomainVersion,
+) -> Result<Response, ContractError> {
+    let (mut deps, env, info) = ctx;
+
+    if additional_participants == 0 {
+        return Err(ContractError::CustomError {
+            val: "Additional participants must be greater than 0".to_string(),
+        });
+    }
+
+    helpers::check_domain_enabled(deps.storage, &domain_version)?;
+
+    let message_hash = query::fund_survey_proof(
+        env,
+        &domain_version,
+        &token,
+        time_to_expire,
+        &survey_id,
+        additional_participants,
+    )?;
+
+    // Funding only brings money in, so a single manager signature is enough
+    helpers::auth_validations(
+        &mut deps,
+        env,
+        token.clone(),
+        message_hash,
+        &signatures,
+        time_to_expire,
+        1,
+    )?;
+
+    let mut survey_info = surveys()
+        .may_load(deps.storage, &survey_id)?
+        .ok_or(ContractError::SurveyNotFound {})?;
+
+    if survey_info.is_cancelled {
+        return Err(ContractError::SurveyAlreadyCancelled {});
+    }
+
+    if survey_info.is_expired(env.block.time.seconds()) {
+        return Err(ContractError::SurveyExpired {});
+    }
+
+    let amount_to_fund = (additional_participants as u128)
+        .checked_mul(survey_info.reward_amount)
+        .ok_or(ContractError::ArithmeticError {})?;
+
+    let amount_sent = cw_utils::must_pay(&info, &survey_info.reward_denom)?;
+
+    if amount_sent != Uint128::from(amount_to_fund) {
+        return Err(ContractError::CustomError {
+            val: format!(
+                "Exact funding required: expected {} {}, got {}",
+                amount_to_fund, survey_info.reward_denom, amount_sent
+            ),
+        });
+    }
+
+    survey_info.participants_limit = survey_info
+        .participants_limit
+        .checked_add(additional_participants)
+        .ok_or(ContractError::ArithmeticError {})?;
+    survey_info.funded_amount = survey_info
+        .funded_amount
+        .checked_add(amount_to_fund)
+        .ok_or(ContractError::ArithmeticError {})?;
+
+    surveys().save(deps.storage, &survey_id, &survey_info)?;
+    transfer::record_liability(deps.storage, &survey_info.reward_denom, amount_to_fund)?;
+
+    let response_data = FundSurveyResponse::new(
+        &survey_id,
+        survey_info.participants_limit,
+        amount_to_fund,
+        env.block.time.seconds(),
+    );
+
+    Ok(Response::new()
+        .set_data(to_json_binary(&response_data)?)
+        .add_attribute("action", "fund_survey")
+        .add_attribute("domain_version", domain_version.as_str())
+        .add_attribute("survey_id", &survey_id)
+        .add_event(
+            Event::new("survey_funded")
+                .add_attribute("survey_id", survey_id)
+                .add_attribute("funder", info.sender)
+                .add_attribute(
+                    "additional_participants",
+                    additional_participants.to_string(),
+                )
+                .add_attribute(
+                    "participants_limit",
+                    survey_info.participants_limit.to_string(),
+                )
+                .add_attribute("amount", amount_to_fund.to_string())
+                .add_attribute("denom", survey_info.reward_denom),
+        ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_survey(
+    ctx: (DepsMut, &Env, MessageInfo),
+    adapter: &impl TransferAdapter,
+    signatures: Vec<ManagerSignature>,
+    token: String,
+    time_to_expire: u64,
+    survey_id: String,
+    domain_version: DomainVersion,
+) -> Result<Response, ContractError> {
+    let (mut deps, env, _info) = ctx;
+
+    helpers::check_domain_enabled(deps.storage, &domain_version)?;
+
+    let message_hash =
+        query::cancel_survey_proof(env, &domain_version, &token, time_to_expire, &survey_id)?;
+
+    // The signatures needed depend on how much of the funding goes back to the creator
+    let survey_info = surveys()
+        .may_load(deps.storage, &survey_id)?
+        .ok_or(ContractError::SurveyNotFound {})?;
+    let refund_amount =
+        survey_info
+            .funded_amount
+            .saturating_sub(query::get_survey_rewards_amount_paid(
+                deps.as_ref(),
+                &survey_id,
+            )?);
+    let required_signatures =
+        helpers::required_signatures(deps.storage, &survey_info.reward_denom, refund_amount)?;
+
+    helpers::auth_validations(
+        &mut deps,
+        env,
+        token.clone(),
+        message_hash,
+        &signatures,
+        time_to_expire,
+        required_signatures,
+    )?;
+
+    // Mark survey as cancelled
+    let survey_info = surveys().update(
+        deps.storage,
+        &survey_id,
+        |survey_info| -> Result<SurveyInfo, ContractError> {
+            let mut survey = survey_info.ok_or(ContractError::SurveyNotFound {})?;
+            if survey.is_cancelled {
+                return Err(ContractError::SurveyAlreadyCancelled {});
+            }
+            survey.is_cancelled = true;
+            Ok(survey)
+        },
+    )?;
+
+    let reward_denom = survey_info.reward_denom.clone();
+    let (return_amount, submsg) =
+        refund_creator(deps.branch(), env, adapter, &survey_id, survey_info)?;
+
+    let response_data =
+        CancelSurveyResponse::new(&survey_id, return_amount, env.block.time.seconds());
+
+    Ok(Response::new()
+        .set_data(to_json_binary(&response_data)?)
+        .add_submessage(submsg)
+        .add_attribute("action", "cancel_survey")
+        .add_attribute("domain_version", domain_version.as_str())
+        .add_attribute("survey_id", survey_id)
+        .add_attribute("amount", return_amount.to_string())
+        .add_attribute("denom", reward_denom)
+        .add_event(helpers::ibc_message_event("cancel_survey: refund creator")))
+}
+
+/// Closes a survey whose deadline has passed and refunds the unspent funding to its
+/// creator. Anyone can call this once the survey has expired.
+pub fn close_expired_survey(
+    ctx: (DepsMut, &Env, MessageInfo),
+    adapter: &impl TransferAdapter,
+    survey_id: String,
+) -> Result<Response, ContractError> {
+    let (mut deps, env, _info) = ctx;
+
+    let survey_info = surveys().update(
+        deps.storage,
+        &survey_id,
+        |survey_info| -> Result<SurveyInfo, ContractError> {
+            let mut survey = survey_info.ok_or(ContractError::SurveyNotFound {})?;
+            if survey.is_cancelled {
+                return Err(ContractError::SurveyAlreadyCancelled {});
+            }
+            if !survey.is_expired(env.block.time.seconds()) {
+                return Err(ContractError::SurveyNotExpired {});
+            }
+            survey.is_cancelled = true;
+            Ok(survey)
+        },
+    )?;
+
+    let reward_denom = survey_info.reward_denom.clone();
+    let (return_amount, submsg) =
+        refund_creator(deps.branch(), env, adapter, &survey_id, survey_info)?;
+
+    let response_data =
+        CancelSurveyResponse::new(&survey_id, return_amount, env.block.time.seconds());
+
+    Ok(Response::new()
+        .set_data(to_json_binary(&response_data)?)
+        .add_submessage(submsg)
+        .add_attribute("action", "close_expired_survey")
+        .add_attribute("survey_id", survey_id)
+        .add_attribute("amount", return_amount.to_string())
+        .add_attribute("denom", reward_denom)
+        .add_event(helpers::ibc_message_event(
+            "close_expired_survey: refund creator",
+        )))
+}
+
+// Sends the unspent part of a survey's funding back to its creator
+fn refund_creator(
+    deps: DepsMut,
+    env: &Env,
+    adapter: &impl TransferAdapter,
+    survey_id: &str,
+    survey_info: SurveyInfo,
+) -> Result<(u128, SubMsg), ContractError> {
+    let reward_denom = survey_info.reward_denom;
+
+    let funded_amount = survey_info.funded_amount;
+
+    let paid_amount = query::get_survey_rewards_amount_paid(deps.as_ref(), survey_id)?;
+
+    // This should never happen - indicates critical accounting error
+    if paid_amount > funded_amount {
+        return Err(ContractError::CustomError {
+            val: format!(
+                "Critical accounting error: paid amount ({}) exceeds funded amount ({})",
+                paid_amount, funded_amount
+            ),
+        });
+    }
+
+    let return_amount = funded_amount - paid_amount;
+
+    if return_amount == 0 {
+        return Err(ContractError::NothingToRefund {});
+    }
+
+    let submsg = transfer::send_transfer(
+        deps,
+        env,
+        adapter,
+        Transfer {
+            kind: TransferKind::Refund,
+            survey_id: survey_id.to_string(),
+            recipient: survey_info.survey_creator,
+            coin: Coin::new(return_amount, &reward_denom),
+        },
+    )?;
+
+    Ok((return_amount, submsg))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pay_rewards(
+    ctx: (DepsMut, &Env, MessageInfo),
+    adapter: &impl TransferAdapter,
+    signatures: Vec<ManagerSignature>,
+    token: String,
+    time_to_expire: u64,
+    survey_ids: Vec<String>,
+    participants: Vec<String>,
+    domain_version: DomainVersion,
+) -> Result<Response, ContractError> {
+    let (mut deps, env, _info) = ctx;
+
+    if survey_ids.len() != participants.len() {
+        return Err(ContractError::ArrayLengthMismatch {});
+    }
+
+    if survey_ids.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "Empty arrays not allowed".to_string(),
+        });
+    }
+
+    helpers::check_domain_enabled(deps.storage, &domain_version)?;
+
+    let message_hash = query::pay_rewards_proof(
+        env,
+        &domain_version,
+        &token,
+        time_to_expire,
+        survey_ids.clone(),
+        participants.clone(),
+    )?;
+
+    // Calculate total rewards needed and verify contract balance
+    let mut total_rewards_needed = 0u128;
+    let mut reward_denom: Option<String> = None;
+
+    for survey_id in &survey_ids {
+        let survey_info = surveys().load(deps.storage, survey_id)?;
+
+        if survey_info.is_cancelled {
+            return Err(ContractError::SurveyAlreadyCancelled {});
+        }
+
+        if survey_info.is_expired(env.block.time.seconds()) {
+            return Err(ContractError::SurveyExpired {});
+        }
+
+        if survey_info.participants_rewarded >= survey_info.participants_limit {
+            return Err(ContractError::AllParticipantsRewarded {});
+        }
+
+        total_rewards_needed = total_rewards_needed
+            .checked_add(survey_info.reward_amount)
+            .ok_or(ContractError::ArithmeticError {})?;
+
+        // Ensure all surveys use the same reward_denom
+        match &reward_denom {
+            None => reward_denom = Some(survey_info.reward_denom.clone()),
+            Some(denom) => {
+                if denom != &survey_info.reward_denom {
+                    return Err(ContractError::CustomError {
+                        val: "All surveys in batch must use same reward denomination".to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    let reward_denom = reward_denom.ok_or(ContractError::CustomError {
+        val: "No reward denomination found".to_string(),
+    })?;
+
+    // The whole batch counts towards the signature threshold
+    let required_signatures =
+        helpers::required_signatures(deps.storage, &reward_denom, total_rewards_needed)?;
+
+    helpers::auth_validations(
+        &mut deps,
+        env,
+        token.clone(),
+        message_hash,
+        &signatures,
+        time_to_expire,
+        required_signatures,
+    )?;
+
+    // Check the batch is covered by the committed funds and the contract holds them
+    transfer::ensure_solvent(deps.as_ref(), env, &reward_denom, total_rewards_needed)?;
+
+    let mut messages: Vec<SubMsg> = Vec::new();
+    let mut rewards = 0u128;
+
+    for (survey_id, participant) in survey_ids.iter().zip(participants.iter()) {
+        let (_, participant) = helpers::validate_account(deps.as_ref(), participant)?;
+
+        let already_rewarded =
+            survey_rewarded_users().has(deps.storage, (survey_id.as_str(), &participant));
+
+        if already_rewarded {
+            return Err(ContractError::UserAlreadyRewarded {});
+        }
+
+        let mut survey_info = surveys().load(deps.storage, survey_id)?;
+        let reward_amount = survey_info.reward_amount;
+
+        // Double-check survey state before creating transfer, earlier entries of the
+        // batch may have used up the remaining participant slots
+        if survey_info.participants_rewarded >= survey_info.participants_limit {
+            return Err(ContractError::AllParticipantsRewarded {});
+        }
+
+        let submsg = transfer::send_transfer(
+            deps.branch(),
+            env,
+            adapter,
+            Transfer {
+                kind: TransferKind::Reward,
+                survey_id: survey_id.clone(),
+                recipient: participant.clone(),
+                coin: Coin::new(reward_amount, &survey_info.reward_denom),
+            },
+        )?;
+
+        messages.push(submsg);
+
+        rewards = rewards
+            .checked_add(reward_amount)
+            .ok_or(ContractError::ArithmeticError {})?;
+
+        survey_info.participants_rewarded += 1;
+
+        surveys().save(deps.storage, survey_id, &survey_info)?;
+
+        // mark user as rewarded
+        survey_rewarded_users().save(
+            deps.storage,
+            (survey_id.as_str(), &participant),
+            &RewardedUser {
+                participant: participant.clone(),
+            },
+        )?;
+    }
+
+    let number_of_rewards = participants.len();
+
+    let response_data =
+        PayRewardsResponse::new(survey_ids, rewards, participants, env.block.time.seconds());
+
+    Ok(Response::new()
+        .set_data(to_json_binary(&response_data)?)
+        .add_submessages(messages)
+        .add_attribute("action", "pay_rewards")
+        .add_attribute("domain_version", domain_version.as_str())
+        .add_attribute("number_of_rewards", number_of_rewards.to_string())
+        .add_event(helpers::ibc_message_event(
+            "pay_rewards: distribute survey rewards",
+        )))
+}
+
+/// Undoes the bookkeeping of a reward payout that never reached the participant, so the
+/// reward can be paid again through `pay_rewards`.
+///
+/// Returns `false` when the survey has been cancelled in the meantime: its refund already
+/// accounted for the reward as paid, so the caller has to keep the funds recoverable instead.
+pub fn revert_reward(
+    storage: &mut dyn Storage,
+    survey_id: &str,
+    participant: &Addr,
+) -> Result<bool, ContractError> {
+    let mut survey_info = surveys()
+        .may_load(storage, survey_id)?
+        .ok_or(ContractError::SurveyNotFound {})?;
+
+    if survey_info.is_cancelled {
+        return Ok(false);
+    }
+
+    survey_info.participants_rewarded = survey_info
+        .participants_rewarded
+        .checked_sub(1)
+        .ok_or(ContractError::ArithmeticError {})?;
+
+    surveys().save(storage, survey_id, &survey_info)?;
+    survey_rewarded_users().remove(storage, (survey_id, participant))?;
+
+    // The reward is back in the contract and owed to the survey again
+    transfer::record_liability(
+        storage,
+        &survey_info.reward_denom,
+        survey_info.reward_amount,
+    )?;
+
+    Ok(true)
+}
+
+/// Re-sends every payout parked in `recoveries` for a recovery address. Each contract
+/// stores its recoveries in its own shape, anything convertible back into a [`Transfer`].
+///
+/// The recoveries are dispatched through the same adapter as regular payouts, so they
+/// end up parked again (under the new receiver) if they fail a second time.
+pub fn recover<T>(
+    ctx: (DepsMut, &Env, MessageInfo),
+    adapter: &impl TransferAdapter,
+    recoveries: &Map<&Addr, Vec<T>>,
+    addr: Option<String>,
+    receiver: Option<String>,
+) -> Result<Response, ContractError>
+where
+    T: Into<Transfer> + Serialize + DeserializeOwned,
+{
+    let (mut deps, env, info) = ctx;
+
+    let recovery_addr = match addr {
+        Some(addr) => helpers::lookup_account(deps.as_ref(), &addr)?,
+        None => info.sender.clone(),
+    };
+
+    helpers::check_can_act_for(deps.as_ref(), &info.sender, &recovery_addr)?;
+
+    let parked = recoveries
+        .may_load(deps.storage, &recovery_addr)?
+        .unwrap_or_default();
+
+    if parked.is_empty() {
+        return Err(ContractError::NothingToRecover {});
+    }
+
+    recoveries.remove(deps.storage, &recovery_addr);
+
+    let receiver = match receiver {
+        Some(receiver) => helpers::validate_account(deps.as_ref(), &receiver)?.1,
+        None => recovery_addr.clone(),
+    };
+
+    // Managers acting for someone else only re-send to them. Remote recovery addresses
+    // can't send this message themselves, so the owner can redirect on their behalf.
+    if receiver != recovery_addr
+        && info.sender != recovery_addr
+        && helpers::check_is_contract_owner(deps.as_ref(), info.sender.clone()).is_err()
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let number_of_transfers = parked.len();
+    let mut messages = Vec::with_capacity(number_of_transfers);
+
+    for recovery in parked {
+        let submsg = transfer::send_transfer(
+            deps.branch(),
+            env,
+            adapter,
+            Transfer {
+                recipient: receiver.clone(),
+                ..recovery.into()
+            },
+        )?;
+
+        messages.push(submsg);
+    }
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "recover")
+        .add_attribute("recovery_addr", recovery_addr)
+        .add_attribute("receiver", receiver)
+        .add_attribute("number_of_transfers", number_of_transfers.to_string())
+        .add_event(helpers::ibc_message_event(
+            "recover: resend failed transfers",
+        )))
+}
+
+pub fn set_manager(
+    ctx: (DepsMut, &Env, MessageInfo),
+    manager_addr: &str,
+    pub_key: String,
+    key_type: KeyType,
+    status: bool,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    let sender = info.sender;
+
+    helpers::check_is_contract_owner(deps.as_ref(), sender)?;
+
+    let (_, manager_addr) = helpers::validate_account(deps.as_ref(), manager_addr)?;
+
+    let enc_pub_key = Binary::from_base64(&pub_key)?;
+    helpers::validate_pub_key(&key_type, &enc_pub_key)?;
+
+    let key_owner = managers()
+        .idx
+        .pub_key
+        .item(deps.storage, enc_pub_key.to_vec())?;
+    if key_owner.is_some_and(|(_, manager)| manager.address != manager_addr) {
+        return Err(ContractError::PubKeyAlreadyRegistered {});
+    }
+
+    if managers().has(deps.storage, &manager_addr) {
+        managers().update(
+            deps.storage,
+            &manager_addr,
+            |manager_info| -> Result<ManagerInfo, ContractError> {
+                if let Some(mut info) = manager_info {
+                    info.status = status;
+                    info.pub_key = enc_pub_key;
+                    info.key_type = key_type.clone();
+                    Ok(info)
+                } else {
+                    Err(ContractError::InvalidManager {})
+                }
+            },
+        )?;
+    } else {
+        let manager_info = ManagerInfo {
+            address: manager_addr.clone(),
+            pub_key: enc_pub_key,
+            key_type: key_type.clone(),
+            status,
+        };
+        managers().save(deps.storage, &manager_addr, &manager_info)?;
+    }
+
+    if !status {
+        helpers::check_signature_policies(deps.as_ref())?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_manager")
+        .add_attribute("manager", manager_addr.to_string())
+        .add_attribute("pub_key", pub_key)
+        .add_attribute("key_type", key_type.as_str())
+        .add_attribute("status", status.to_string()))
+}
+
+/// Owner only. Deletes a manager outright, freeing its public key.
+pub fn remove_manager(
+    ctx: (DepsMut, &Env, MessageInfo),
+    manager_addr: &str,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    let manager_addr = helpers::lookup_account(deps.as_ref(), manager_addr)?;
+
+    if !managers().has(deps.storage, &manager_addr) {
+        return Err(ContractError::InvalidManager {});
+    }
+
+    managers().remove(deps.storage, &manager_addr)?;
+    helpers::check_signature_policies(deps.as_ref())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_manager")
+        .add_attribute("manager", manager_addr.to_string()))
+}
+
+/// Owner only. Proposes `new_owner`, who becomes owner once they accept. Replaces any
+/// pending proposal.
+pub fn propose_owner(
+    ctx: (DepsMut, &Env, MessageInfo),
+    new_owner: String,
+    expiry: Option<u64>,
+) -> Result<Response, ContractError> {
+    let (deps, env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+
+    if expiry.is_some_and(|expiry| expiry <= env.block.time.seconds()) {
+        return Err(ContractError::CustomError {
+            val: "Ownership proposal expiry must be in the future".to_string(),
+        });
+    }
+
+    PENDING_OWNER.save(
+        deps.storage,
+        &PendingOwner {
+            owner: new_owner.clone(),
+            expires_at: expiry,
+        },
+    )?;
+
+    let mut response = Response::new()
+        .add_attribute("action", "propose_owner")
+        .add_attribute("pending_owner", new_owner.to_string());
+    if let Some(expiry) = expiry {
+        response = response.add_attribute("expiry", expiry.to_string());
+    }
+
+    Ok(response)
+}
+
+/// Pending owner only. Completes the ownership transfer before the proposal expires.
+pub fn accept_ownership(ctx: (DepsMut, &Env, MessageInfo)) -> Result<Response, ContractError> {
+    let (deps, env, info) = ctx;
+
+    let pending_owner = PENDING_OWNER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingOwner {})?;
+
+    if pending_owner.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if pending_owner
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= env.block.time.seconds())
+    {
+        return Err(ContractError::OwnershipProposalExpired {});
+    }
+
+    CONFIG.update(
+        deps.storage,
+        |mut config| -> Result<Config, ContractError> {
+            config.owner = Some(pending_owner.owner.clone());
+            Ok(config)
+        },
+    )?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_ownership")
+        .add_attribute("new_owner", pending_owner.owner.to_string()))
+}
+
+/// Owner only. Withdraws the pending ownership proposal.
+pub fn cancel_ownership_proposal(
+    ctx: (DepsMut, &Env, MessageInfo),
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    if !PENDING_OWNER.exists(deps.storage) {
+        return Err(ContractError::NoPendingOwner {});
+    }
+
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "cancel_ownership_proposal"))
+}
+
+/// Owner only. Leaves the contract without an owner for good, disabling every owner
+/// only message. Any pending proposal is dropped.
+pub fn renounce_ownership(ctx: (DepsMut, &Env, MessageInfo)) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    CONFIG.update(
+        deps.storage,
+        |mut config| -> Result<Config, ContractError> {
+            config.owner = None;
+            Ok(config)
+        },
+    )?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "renounce_ownership"))
+}
+
+/// Deletes up to `limit` used proof tokens whose proofs have expired, oldest first. A
+/// pruned token cannot be replayed, as its signed `time_to_expire` has passed. Callable
+/// by anyone.
+pub fn prune_proof_tokens(
+    ctx: (DepsMut, &Env, MessageInfo),
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let (deps, env, _info) = ctx;
+    let limit = limit.unwrap_or(DEFAULT_PRUNE_LIMIT).min(MAX_PRUNE_LIMIT) as usize;
+
+    // Only buckets that ended before the current block hold nothing but expired tokens
+    let current_bucket = proof_token_bucket(env.block.time.seconds());
+    let expired = PROOF_TOKEN_EXPIRIES
+        .keys(
+            deps.storage,
+            None,
+            Some(Bound::exclusive((current_bucket, ""))),
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (bucket, token) in expired.iter() {
+        PROOF_TOKEN_EXPIRIES.remove(deps.storage, (*bucket, token));
+        USED_PROOF_TOKENS.remove(deps.storage, token);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "prune_proof_tokens")
+        .add_attribute("pruned", expired.len().to_string()))
+}
+
+/// Owner only. Enables or retires a signing domain version. At least one version must
+/// stay enabled so managers can still sign proofs.
+pub fn set_signing_domain(
+    ctx: (DepsMut, &Env, MessageInfo),
+    version: DomainVersion,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    SIGNING_DOMAINS.save(deps.storage, version.as_str(), &enabled)?;
+
+    let mut any_enabled = false;
+    for other in DomainVersion::ALL.iter() {
+        any_enabled |= helpers::is_domain_enabled(deps.storage, other)?;
+    }
+    if !any_enabled {
+        return Err(ContractError::InvalidConfig {
+            reason: "at least one signing domain must stay enabled".to_string(),
+        });
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_signing_domain")
+        .add_attribute("domain_version", version.as_str())
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+/// Owner only. Sets the signature threshold of `policy.denom`, replacing any existing
+/// policy for the denom
+pub fn set_signature_policy(
+    ctx: (DepsMut, &Env, MessageInfo),
+    policy: SignaturePolicy,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+    helpers::validate_signature_policy(deps.as_ref(), &policy)?;
+
+    SIGNATURE_POLICIES.save(deps.storage, &policy.denom, &policy)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_signature_policy")
+        .add_attribute("denom", policy.denom)
+        .add_attribute("value_limit", policy.value_limit.to_string())
+        .add_attribute("threshold", policy.threshold.to_string()))
+}
+
+/// Owner only. Operations in `denom` go back to needing a single signature.
+pub fn remove_signature_policy(
+    ctx: (DepsMut, &Env, MessageInfo),
+    denom: String,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    if !SIGNATURE_POLICIES.has(deps.storage, &denom) {
+        return Err(ContractError::CustomError {
+            val: format!("No signature policy for denom {denom}"),
+        });
+    }
+
+    SIGNATURE_POLICIES.remove(deps.storage, &denom);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_signature_policy")
+        .add_attribute("denom", denom))
+}
+
+/// Routes accounts with `route.prefix` over `route.channel_id` and its forwarding hops,
+/// replacing any existing route for the prefix
+pub fn set_route(
+    ctx: (DepsMut, &Env, MessageInfo),
+    route: Route,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+    helpers::validate_route(&route)?;
+
+    ROUTES.save(deps.storage, &route.prefix, &route)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_route")
+        .add_attribute("prefix", route.prefix)
+        .add_attribute("channel_id", route.channel_id)
+        .add_attribute("hops", route.forward.len().to_string()))
+}
+
+pub fn remove_route(
+    ctx: (DepsMut, &Env, MessageInfo),
+    prefix: String,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+
+    if !ROUTES.has(deps.storage, &prefix) {
+        return Err(ContractError::CustomError {
+            val: format!("No route for prefix {prefix}"),
+        });
+    }
+
+    ROUTES.remove(deps.storage, &prefix);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_route")
+        .add_attribute("prefix", prefix))
+}
+
+/// Replaces the timeouts applied to outgoing IBC transfers
+pub fn update_config(
+    ctx: (DepsMut, &Env, MessageInfo),
+    ibc_timeout_seconds: u64,
+    ibc_timeout_block: Option<IbcTimeoutBlock>,
+) -> Result<Response, ContractError> {
+    let (deps, _env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+    helpers::validate_ibc_timeout(ibc_timeout_seconds, ibc_timeout_block.as_ref())?;
+
+    let config = CONFIG.update(
+        deps.storage,
+        |mut config| -> Result<Config, ContractError> {
+            config.ibc_timeout_seconds = ibc_timeout_seconds;
+            config.ibc_timeout_block = ibc_timeout_block;
+            Ok(config)
+        },
+    )?;
+
+    let timeout_block = config
+        .ibc_timeout_block
+        .map(|block| format!("{}-{}", block.revision, block.height))
+        .unwrap_or_else(|| "none".to_string());
+
+    Ok(Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("ibc_timeout_seconds", ibc_timeout_seconds.to_string())
+        .add_attribute("ibc_timeout_block", timeout_block))
+}
+
+/// Sends the balance of `denom` above the tracked liabilities to `recipient`. Funds owed
+/// to surveys or parked for recovery are never touched.
+pub fn sweep_surplus(
+    ctx: (DepsMut, &Env, MessageInfo),
+    denom: String,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let (deps, env, info) = ctx;
+    helpers::check_is_contract_owner(deps.as_ref(), info.sender)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let balance = helpers::query_contract_balance(&deps.querier, &env.contract.address, &denom)?;
+    let liabilities = LIABILITIES
+        .may_load(deps.storage, &denom)?
+        .unwrap_or_default();
+
+    let surplus = balance.u128().saturating_sub(liabilities);
+
+    if surplus == 0 {
+        return Err(ContractError::NoSurplus {});
+    }
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin::new(surplus, &denom)],
+        })
+        .add_attribute("action", "sweep_surplus")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", surplus.to_string())
+        .add_attribute("denom", denom))
+}
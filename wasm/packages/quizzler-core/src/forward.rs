@@ -0,0 +1,68 @@
+use crate::msg::Route;
+use cosmwasm_schema::cw_serde;
+
+pub const DEFAULT_FORWARD_PORT: &str = "transfer";
+
+/// The `forward` object read by packet-forward-middleware from an ICS-20 memo
+#[cw_serde]
+pub struct ForwardMemo {
+    pub receiver: String,
+    pub port: String,
+    pub channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<Box<PacketMemo>>,
+}
+
+/// Memo of a packet handled by packet-forward-middleware
+#[cw_serde]
+pub struct PacketMemo {
+    pub forward: ForwardMemo,
+}
+
+/// How a transfer to a multi-hop route leaves this chain
+pub struct Forwarding {
+    /// Account on the first hop's chain the packet is addressed to
+    pub receiver: String,
+    pub forward: ForwardMemo,
+}
+
+/// Builds the packet-forward-middleware memo carrying the funds through every hop of
+/// `route` to `receiver`, or `None` when the route reaches `receiver` directly.
+///
+/// Packet-forward-middleware holds the ack of the first packet until the last hop
+/// resolves, so a transfer failing on any hop comes back to this contract as an error
+/// ack (or a timeout) of the packet it sent, with the funds refunded.
+pub fn forwarding(route: &Route, receiver: &str) -> Option<Forwarding> {
+    let first = route.forward.first()?;
+
+    let forward = route.forward.iter().enumerate().rev().fold(
+        None,
+        |next: Option<ForwardMemo>, (index, hop)| {
+            let hop_receiver = route
+                .forward
+                .get(index + 1)
+                .map_or(receiver, |next_hop| next_hop.receiver.as_str());
+
+            Some(ForwardMemo {
+                receiver: hop_receiver.to_string(),
+                port: hop
+                    .port
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_FORWARD_PORT.to_string()),
+                channel: hop.channel.clone(),
+                timeout: hop.timeout.clone(),
+                retries: hop.retries,
+                next: next.map(|forward| Box::new(PacketMemo { forward })),
+            })
+        },
+    )?;
+
+    Some(Forwarding {
+        receiver: first.receiver.clone(),
+        forward,
+    })
+}
@@ -0,0 +1,355 @@
+use crate::error::ContractError;
+use crate::msg::{Manager, ManagerSignature, Route, SignaturePolicy};
+use crate::state::{
+    managers, proof_token_bucket, DomainVersion, KeyType, ManagerInfo, CONFIG,
+    MAX_IBC_TIMEOUT_SECONDS, MAX_PROOF_WINDOW_SECONDS, PROOF_TOKEN_EXPIRIES, ROUTES,
+    SIGNATURE_POLICIES, SIGNING_DOMAINS, USED_PROOF_TOKENS,
+};
+use crate::transfer::Destination;
+use cosmwasm_std::{
+    Addr, BalanceResponse, BankQuery, Binary, Deps, DepsMut, Empty, Env, IbcTimeoutBlock, Order,
+    QuerierWrapper, QueryRequest, StdResult, Storage, Uint128,
+};
+
+pub fn map_validate(deps: Deps, managers: &[Manager]) -> Result<Vec<ManagerInfo>, ContractError> {
+    managers
+        .iter()
+        .map(|admin| {
+            let (_, validated_addr) = validate_account(deps, &admin.addr)?;
+            let pub_key = Binary::from_base64(&admin.pub_key)?;
+            validate_pub_key(&admin.key_type, &pub_key)?;
+            Ok(ManagerInfo {
+                address: validated_addr,
+                pub_key,
+                key_type: admin.key_type.clone(),
+                status: true,
+            })
+        })
+        .collect()
+}
+
+/// Checks that `receiver` is either an account of the host chain or a bech32 account on
+/// a chain the contract has a route to. Returns where the account lives together with
+/// the account.
+pub fn validate_account(deps: Deps, receiver: &str) -> Result<(Destination, Addr), ContractError> {
+    if let Ok(addr) = deps.api.addr_validate(receiver) {
+        return Ok((Destination::Local, addr));
+    }
+
+    let route = find_route(deps.storage, receiver)?;
+
+    Ok((Destination::Remote { route }, Addr::unchecked(receiver)))
+}
+
+/// Parses an account that state may already be stored under. Unlike `validate_account`
+/// no route is needed, so the account stays reachable after its route is removed.
+pub fn lookup_account(deps: Deps, account: &str) -> Result<Addr, ContractError> {
+    if let Ok(addr) = deps.api.addr_validate(account) {
+        return Ok(addr);
+    }
+
+    if bech32::decode(account).is_err() {
+        return Err(ContractError::InvalidAccount {
+            receiver: account.to_string(),
+        });
+    }
+
+    Ok(Addr::unchecked(account))
+}
+
+/// The route used to reach `receiver`, picked from its bech32 prefix
+pub fn find_route(storage: &dyn Storage, receiver: &str) -> Result<Route, ContractError> {
+    let Ok((prefix, _, _)) = bech32::decode(receiver) else {
+        return Err(ContractError::InvalidAccount {
+            receiver: receiver.to_string(),
+        });
+    };
+
+    ROUTES
+        .may_load(storage, &prefix)?
+        .ok_or(ContractError::NoRoute {
+            receiver: receiver.to_string(),
+        })
+}
+
+pub fn validate_route(route: &Route) -> Result<(), ContractError> {
+    let valid_prefix = !route.prefix.is_empty()
+        && route
+            .prefix
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+
+    if !valid_prefix {
+        return Err(ContractError::InvalidConfig {
+            reason: format!("invalid bech32 prefix {}", route.prefix),
+        });
+    }
+
+    let channels =
+        std::iter::once(&route.channel_id).chain(route.forward.iter().map(|hop| &hop.channel));
+    for channel_id in channels {
+        if !is_valid_channel(channel_id) {
+            return Err(ContractError::InvalidConfig {
+                reason: format!("invalid channel id {channel_id}"),
+            });
+        }
+    }
+
+    for hop in &route.forward {
+        if hop.receiver.is_empty() {
+            return Err(ContractError::InvalidConfig {
+                reason: format!("missing forward receiver for channel {}", hop.channel),
+            });
+        }
+        if hop.port.as_ref().is_some_and(|port| port.is_empty()) {
+            return Err(ContractError::InvalidConfig {
+                reason: format!("empty forward port for channel {}", hop.channel),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn is_valid_channel(channel_id: &str) -> bool {
+    channel_id
+        .strip_prefix("channel-")
+        .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Checks `pub_key` has the length and encoding of a `key_type` key. Secp256k1 keys can
+/// be compressed or uncompressed.
+pub fn validate_pub_key(key_type: &KeyType, pub_key: &Binary) -> Result<(), ContractError> {
+    let valid = match key_type {
+        KeyType::Ed25519 => pub_key.len() == 32,
+        KeyType::Secp256k1 => matches!(
+            (pub_key.len(), pub_key.first()),
+            (33, Some(0x02 | 0x03)) | (65, Some(0x04))
+        ),
+    };
+
+    if !valid {
+        return Err(ContractError::InvalidPubKey {
+            key_type: key_type.as_str().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn is_domain_enabled(storage: &dyn Storage, version: &DomainVersion) -> StdResult<bool> {
+    Ok(SIGNING_DOMAINS
+        .may_load(storage, version.as_str())?
+        .unwrap_or_else(|| version.enabled_by_default()))
+}
+
+pub fn check_domain_enabled(
+    storage: &dyn Storage,
+    version: &DomainVersion,
+) -> Result<(), ContractError> {
+    if !is_domain_enabled(storage, version)? {
+        return Err(ContractError::SigningDomainNotEnabled {
+            version: version.as_str().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks the proof has not expired or been used, then that `signatures` over `message`
+/// come from at least `required_signatures` different active managers. Every signature
+/// must be valid, a manager signing twice is rejected.
+pub fn auth_validations(
+    deps: &mut DepsMut,
+    env: &Env,
+    token: String,
+    message: Binary,
+    signatures: &[ManagerSignature],
+    time_to_expire: u64,
+    required_signatures: u32,
+) -> Result<(), ContractError> {
+    let current_block_time = env.block.time.seconds();
+    if time_to_expire < current_block_time {
+        return Err(ContractError::ProofExpired {});
+    }
+
+    if time_to_expire > current_block_time.saturating_add(MAX_PROOF_WINDOW_SECONDS) {
+        return Err(ContractError::ProofWindowExceeded {});
+    }
+
+    if USED_PROOF_TOKENS.has(deps.storage, &token) {
+        return Err(ContractError::TokenAlreadyUsed {});
+    }
+
+    let mut signers: Vec<Addr> = Vec::with_capacity(signatures.len());
+
+    for manager_signature in signatures {
+        let pub_key = Binary::from_base64(&manager_signature.pub_key)?;
+        let signature = Binary::from_base64(&manager_signature.signature)?;
+
+        // Verify pub key belongs to an active manager
+        let Some((_, manager_info)) = managers()
+            .idx
+            .pub_key
+            .item(deps.storage, pub_key.to_vec())?
+            .filter(|(_, manager_info)| manager_info.status)
+        else {
+            return Err(ContractError::InvalidSigner {});
+        };
+
+        if signers.contains(&manager_info.address) {
+            return Err(ContractError::DuplicateSigner {});
+        }
+
+        // The proof is already a sha256 digest, which is what secp256k1 expects as message hash
+        let result = match manager_info.key_type {
+            KeyType::Ed25519 => deps.api.ed25519_verify(&message, &signature, &pub_key)?,
+            KeyType::Secp256k1 => deps.api.secp256k1_verify(&message, &signature, &pub_key)?,
+        };
+
+        if !result {
+            return Err(ContractError::InvalidMessageHash {});
+        }
+
+        signers.push(manager_info.address);
+    }
+
+    // A proof always needs at least one signature
+    let required = required_signatures.max(1);
+    let provided = signers.len() as u32;
+    if provided < required {
+        return Err(ContractError::InsufficientSignatures { required, provided });
+    }
+
+    // mark proof token as used only after successful verification, until the proof expires
+    USED_PROOF_TOKENS.save(deps.storage, &token, &time_to_expire)?;
+    PROOF_TOKEN_EXPIRIES.save(
+        deps.storage,
+        (proof_token_bucket(time_to_expire), &token),
+        &Empty {},
+    )?;
+
+    Ok(())
+}
+
+/// Number of manager signatures needed to move `value` of `denom`. Without a policy for
+/// the denom, or up to its value limit, one signature is enough.
+pub fn required_signatures(storage: &dyn Storage, denom: &str, value: u128) -> StdResult<u32> {
+    let required = SIGNATURE_POLICIES
+        .may_load(storage, denom)?
+        .filter(|policy| value > policy.value_limit)
+        .map_or(1, |policy| policy.threshold);
+
+    Ok(required)
+}
+
+pub fn validate_signature_policy(
+    deps: Deps,
+    policy: &SignaturePolicy,
+) -> Result<(), ContractError> {
+    if policy.denom.is_empty() {
+        return Err(ContractError::InvalidConfig {
+            reason: "empty signature policy denom".to_string(),
+        });
+    }
+
+    let active_managers = count_active_managers(deps.storage)?;
+    check_threshold(policy, active_managers)
+}
+
+/// Checks every signature policy can still be met by the active managers. Run after a
+/// manager is removed or disabled, so a threshold can never lock out signed operations.
+pub fn check_signature_policies(deps: Deps) -> Result<(), ContractError> {
+    let active_managers = count_active_managers(deps.storage)?;
+
+    for item in SIGNATURE_POLICIES.range(deps.storage, None, None, Order::Ascending) {
+        let (_, policy) = item?;
+        check_threshold(&policy, active_managers)?;
+    }
+
+    Ok(())
+}
+
+fn count_active_managers(storage: &dyn Storage) -> StdResult<u32> {
+    let mut active_managers = 0u32;
+    for item in managers().range(storage, None, None, Order::Ascending) {
+        let (_, manager) = item?;
+        if manager.status {
+            active_managers += 1;
+        }
+    }
+
+    Ok(active_managers)
+}
+
+fn check_threshold(policy: &SignaturePolicy, active_managers: u32) -> Result<(), ContractError> {
+    if policy.threshold == 0 || policy.threshold > active_managers {
+        return Err(ContractError::InvalidConfig {
+            reason: format!(
+                "{} threshold {} must be between 1 and the {} active managers",
+                policy.denom, policy.threshold, active_managers
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn check_is_contract_owner(deps: Deps, sender: Addr) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner.as_ref() != Some(&sender) {
+        Err(ContractError::Unauthorized {})
+    } else {
+        Ok(())
+    }
+}
+
+/// The address itself, the contract owner and active managers may act on behalf of `addr`.
+pub fn check_can_act_for(deps: Deps, sender: &Addr, addr: &Addr) -> Result<(), ContractError> {
+    if sender == addr || check_is_contract_owner(deps, sender.clone()).is_ok() {
+        return Ok(());
+    }
+
+    match managers().may_load(deps.storage, sender)? {
+        Some(manager) if manager.status => Ok(()),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+pub fn query_contract_balance(
+    querier: &QuerierWrapper,
+    addr: &Addr,
+    denom: &str,
+) -> StdResult<Uint128> {
+    let resp: BalanceResponse = querier.query(&QueryRequest::Bank(BankQuery::Balance {
+        address: addr.to_string(),
+        denom: denom.to_string(),
+    }))?;
+    Ok(resp.amount.amount)
+}
+
+pub fn validate_ibc_timeout(
+    ibc_timeout_seconds: u64,
+    ibc_timeout_block: Option<&IbcTimeoutBlock>,
+) -> Result<(), ContractError> {
+    if ibc_timeout_seconds == 0 || ibc_timeout_seconds > MAX_IBC_TIMEOUT_SECONDS {
+        return Err(ContractError::InvalidConfig {
+            reason: format!(
+                "ibc_timeout_seconds must be between 1 and {}",
+                MAX_IBC_TIMEOUT_SECONDS
+            ),
+        });
+    }
+
+    if ibc_timeout_block.is_some_and(|block| block.height == 0) {
+        return Err(ContractError::InvalidConfig {
+            reason: "ibc_timeout_block height must be greater than 0".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn ibc_message_event(context: &str) -> cosmwasm_std::Event {
+    cosmwasm_std::Event::new("ibc_message_added").add_attribute("context", context)
+}
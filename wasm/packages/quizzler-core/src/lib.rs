@@ -0,0 +1,11 @@
+pub mod error;
+pub mod execute;
+pub mod forward;
+pub mod helpers;
+pub mod migrate;
+pub mod msg;
+pub mod query;
+pub mod state;
+pub mod transfer;
+
+pub use crate::error::ContractError;
@@ -0,0 +1,241 @@
+use crate::error::ContractError;
+use crate::msg::Route;
+use crate::state::{
+    managers, proof_token_bucket, survey_rewarded_users, surveys, Config, RewardedUser, SurveyInfo,
+    CONFIG, DEFAULT_IBC_TIMEOUT_SECONDS, PROOF_TOKEN_EXPIRIES, ROUTES, USED_PROOF_TOKENS,
+};
+use crate::transfer;
+use cosmwasm_std::{Addr, Empty, Env, Order, Response, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
+use serde::{Deserialize, Serialize};
+
+/// `Config` as stored before routes, when every payout went to `receiver_prefix`
+/// accounts over `channel_id`
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LegacyConfig {
+    owner: Addr,
+    receiver_prefix: String,
+    channel_id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredConfig {
+    Current(Config),
+    Legacy(LegacyConfig),
+}
+
+const STORED_CONFIG: Item<StoredConfig> = Item::new("config");
+
+/// A survey in any stored layout. Surveys stored before `funded_amount` existed have
+/// neither it nor `ends_at`.
+#[derive(Serialize, Deserialize)]
+struct StoredSurvey {
+    survey_creator: Addr,
+    participants_limit: u32,
+    reward_amount: u128,
+    participants_rewarded: u32,
+    survey_hash: String,
+    is_cancelled: bool,
+    reward_denom: String,
+    ends_at: Option<u64>,
+    funded_amount: Option<u128>,
+}
+
+// The primary map of `surveys()`, read without the indexes
+const STORED_SURVEYS: Map<&str, StoredSurvey> = Map::new("surveys");
+
+/// A rewarded participant, stored as `true` before the participant index
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredRewardedUser {
+    Current(RewardedUser),
+    Legacy(bool),
+}
+
+// The primary map of `survey_rewarded_users()`, read without the index
+const STORED_REWARDED_USERS: Map<(&str, &Addr), StoredRewardedUser> =
+    Map::new("survey_rewarded_users");
+
+/// A used proof token, stored as `true` before tokens expired
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredProofToken {
+    Expiry(u64),
+    Legacy(bool),
+}
+
+// The used proof tokens, read without assuming the layout of an entry
+const STORED_PROOF_TOKENS: Map<&str, StoredProofToken> = Map::new("used_proof_tokens");
+
+/// Converts the state shared by both contracts from the layout of earlier versions.
+///
+/// Every step only rewrites entries still in an old layout, so running the migration
+/// again leaves the state unchanged.
+pub fn migrate_state(storage: &mut dyn Storage, env: &Env) -> Result<Response, ContractError> {
+    let routes = migrate_config(storage)?;
+    let managers = index_managers(storage)?;
+    let surveys = migrate_surveys(storage)?;
+    let rewarded_users = migrate_rewarded_users(storage)?;
+    let proof_tokens = migrate_proof_tokens(storage, env.block.time.seconds())?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("migrated_routes", routes.to_string())
+        .add_attribute("managers", managers.to_string())
+        .add_attribute("migrated_surveys", surveys.to_string())
+        .add_attribute("migrated_rewarded_users", rewarded_users.to_string())
+        .add_attribute("migrated_proof_tokens", proof_tokens.to_string()))
+}
+
+// Converts a config stored before routes into the current config plus a route for its
+// prefix, unless a route for the prefix already exists. Returns the number of routes
+// added.
+fn migrate_config(storage: &mut dyn Storage) -> Result<usize, ContractError> {
+    let StoredConfig::Legacy(legacy) = STORED_CONFIG.load(storage)? else {
+        return Ok(0);
+    };
+
+    CONFIG.save(
+        storage,
+        &Config {
+            owner: Some(legacy.owner),
+            ibc_timeout_seconds: DEFAULT_IBC_TIMEOUT_SECONDS,
+            ibc_timeout_block: None,
+        },
+    )?;
+
+    if ROUTES.has(storage, &legacy.receiver_prefix) {
+        return Ok(0);
+    }
+    ROUTES.save(
+        storage,
+        &legacy.receiver_prefix,
+        &Route {
+            prefix: legacy.receiver_prefix.clone(),
+            channel_id: legacy.channel_id,
+            forward: vec![],
+        },
+    )?;
+
+    Ok(1)
+}
+
+// Writes every manager back through `managers()` so managers stored before the public
+// key index existed can be found by their key. Returns the number of managers.
+fn index_managers(storage: &mut dyn Storage) -> Result<usize, ContractError> {
+    let stored = managers()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (address, manager) in stored.iter() {
+        managers().save(storage, address, manager)?;
+    }
+
+    Ok(stored.len())
+}
+
+// Converts surveys stored before `funded_amount` existed. Such surveys were funded with
+// exactly `participants_limit * reward_amount` when created. Saving them through
+// `surveys()` also adds them to the creator and status indexes, and what an open survey
+// has not paid out yet is recorded as a liability so it can't be swept. Returns the
+// number of surveys converted.
+fn migrate_surveys(storage: &mut dyn Storage) -> Result<usize, ContractError> {
+    let legacy = STORED_SURVEYS
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|entry| match entry {
+            Ok((survey_id, survey)) if survey.funded_amount.is_none() => {
+                Some(Ok((survey_id, survey)))
+            }
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (survey_id, survey) in legacy.iter() {
+        let funded_amount = (survey.participants_limit as u128)
+            .checked_mul(survey.reward_amount)
+            .ok_or(ContractError::ArithmeticError {})?;
+
+        if !survey.is_cancelled {
+            let paid_amount = (survey.participants_rewarded as u128)
+                .checked_mul(survey.reward_amount)
+                .ok_or(ContractError::ArithmeticError {})?;
+            transfer::record_liability(
+                storage,
+                &survey.reward_denom,
+                funded_amount.saturating_sub(paid_amount),
+            )?;
+        }
+
+        // `surveys().save` would fail reading the old entry to update the indexes
+        STORED_SURVEYS.remove(storage, survey_id);
+        surveys().save(
+            storage,
+            survey_id,
+            &SurveyInfo {
+                survey_creator: survey.survey_creator.clone(),
+                participants_limit: survey.participants_limit,
+                reward_amount: survey.reward_amount,
+                participants_rewarded: survey.participants_rewarded,
+                survey_hash: survey.survey_hash.clone(),
+                is_cancelled: survey.is_cancelled,
+                reward_denom: survey.reward_denom.clone(),
+                ends_at: None,
+                funded_amount,
+            },
+        )?;
+    }
+
+    Ok(legacy.len())
+}
+
+// Converts rewarded participants stored before the participant index, saving them
+// through `survey_rewarded_users()` so they are listed by participant. Returns the
+// number of participants converted.
+fn migrate_rewarded_users(storage: &mut dyn Storage) -> Result<usize, ContractError> {
+    let legacy = STORED_REWARDED_USERS
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|entry| match entry {
+            Ok((key, StoredRewardedUser::Legacy(_))) => Some(Ok(key)),
+            Ok((_, StoredRewardedUser::Current(_))) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (survey_id, participant) in legacy.iter() {
+        // `survey_rewarded_users().save` would fail reading the old entry
+        STORED_REWARDED_USERS.remove(storage, (survey_id, participant));
+        survey_rewarded_users().save(
+            storage,
+            (survey_id, participant),
+            &RewardedUser {
+                participant: participant.clone(),
+            },
+        )?;
+    }
+
+    Ok(legacy.len())
+}
+
+// Gives the proof tokens used before tokens expired an expiry so they can be pruned.
+// Their proofs were signed without the signing domain and no longer verify, so the
+// tokens can expire right away. Returns the number of tokens converted.
+fn migrate_proof_tokens(storage: &mut dyn Storage, now: u64) -> Result<usize, ContractError> {
+    let legacy = STORED_PROOF_TOKENS
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|entry| match entry {
+            Ok((token, StoredProofToken::Legacy(_))) => Some(Ok(token)),
+            Ok((_, StoredProofToken::Expiry(_))) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for token in legacy.iter() {
+        USED_PROOF_TOKENS.save(storage, token, &now)?;
+        PROOF_TOKEN_EXPIRIES.save(storage, (proof_token_bucket(now), token), &Empty {})?;
+    }
+
+    Ok(legacy.len())
+}
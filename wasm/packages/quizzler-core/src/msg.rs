@@ -0,0 +1,200 @@
+use crate::state::{DomainVersion, KeyType, SurveyInfo};
+use cosmwasm_schema::cw_serde;
+
+#[cw_serde]
+pub struct Manager {
+    pub addr: String,
+    pub pub_key: String,
+    #[serde(default)]
+    pub key_type: KeyType,
+}
+
+/// A manager's signature over a proof
+#[cw_serde]
+pub struct ManagerSignature {
+    pub pub_key: String,
+    pub signature: String,
+}
+
+/// Creating, cancelling or paying rewards of surveys moving more than `value_limit` of
+/// `denom` needs `threshold` manager signatures, smaller operations need one
+#[cw_serde]
+pub struct SignaturePolicy {
+    pub denom: String,
+    pub value_limit: u128,
+    pub threshold: u32,
+}
+
+/// Accounts with the bech32 `prefix` are paid over `channel_id`. When `forward` is not
+/// empty the chain at the other end of `channel_id` is only the first hop, and
+/// packet-forward-middleware carries the funds through every hop in order.
+#[cw_serde]
+pub struct Route {
+    pub prefix: String,
+    pub channel_id: String,
+    #[serde(default)]
+    pub forward: Vec<ForwardHop>,
+}
+
+/// One packet-forward-middleware hop. `receiver` is the account holding the funds on the
+/// chain the packet arrives at before they are sent on over `port` / `channel`.
+#[cw_serde]
+pub struct ForwardHop {
+    pub receiver: String,
+    pub port: Option<String>,
+    pub channel: String,
+    /// Duration accepted by packet-forward-middleware, e.g. `10m`
+    pub timeout: Option<String>,
+    pub retries: Option<u8>,
+}
+
+#[cw_serde]
+pub struct SurveyResponse {
+    pub survey_id: String,
+    pub survey_creator: String,
+    pub participants_limit: u32,
+    pub reward_amount: u128,
+    pub participants_rewarded: u32,
+    pub survey_hash: String,
+    pub amount_to_fund: u128,
+    pub is_cancelled: bool,
+    pub reward_denom: String,
+    pub ends_at: Option<u64>,
+    pub funded_amount: u128,
+}
+
+impl SurveyResponse {
+    pub fn new(survey_id: String, survey_info: SurveyInfo) -> Self {
+        Self {
+            survey_id,
+            survey_creator: survey_info.survey_creator.to_string(),
+            participants_limit: survey_info.participants_limit,
+            reward_amount: survey_info.reward_amount,
+            participants_rewarded: survey_info.participants_rewarded,
+            survey_hash: survey_info.survey_hash,
+            amount_to_fund: survey_info.participants_limit as u128 * survey_info.reward_amount,
+            is_cancelled: survey_info.is_cancelled,
+            reward_denom: survey_info.reward_denom,
+            ends_at: survey_info.ends_at,
+            funded_amount: survey_info.funded_amount,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct ParticipantRewardResponse {
+    pub survey_id: String,
+    pub reward_amount: u128,
+    pub reward_denom: String,
+}
+
+#[cw_serde]
+pub struct CreateSurveyResponse {
+    pub survey_id: String,
+    pub participants_limit: u32,
+    pub reward_amount: u128,
+    pub reward_denom: String,
+    pub timestamp: u64,
+}
+
+impl CreateSurveyResponse {
+    pub fn new(
+        survey_id: &str,
+        participants_limit: u32,
+        reward_amount: u128,
+        reward_denom: &str,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            survey_id: survey_id.to_string(),
+            participants_limit,
+            reward_amount,
+            reward_denom: reward_denom.to_string(),
+            timestamp,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct CancelSurveyResponse {
+    pub survey_id: String,
+    pub amount_refunded: u128,
+    pub timestamp: u64,
+}
+
+impl CancelSurveyResponse {
+    pub fn new(survey_id: &str, amount: u128, timestamp: u64) -> Self {
+        Self {
+            survey_id: survey_id.to_string(),
+            amount_refunded: amount,
+            timestamp,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct FundSurveyResponse {
+    pub survey_id: String,
+    pub participants_limit: u32,
+    pub amount_funded: u128,
+    pub timestamp: u64,
+}
+
+impl FundSurveyResponse {
+    pub fn new(survey_id: &str, participants_limit: u32, amount: u128, timestamp: u64) -> Self {
+        Self {
+            survey_id: survey_id.to_string(),
+            participants_limit,
+            amount_funded: amount,
+            timestamp,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PayRewardsResponse {
+    pub survey_ids: Vec<String>,
+    pub participants: Vec<String>,
+    pub total_rewards_paid: u128,
+    pub timestamp: u64,
+}
+
+impl PayRewardsResponse {
+    pub fn new(
+        survey_ids: Vec<String>,
+        rewards: u128,
+        participants: Vec<String>,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            survey_ids,
+            total_rewards_paid: rewards,
+            participants,
+            timestamp,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct SolvencyResponse {
+    pub denom: String,
+    pub balance: u128,
+    pub liabilities: u128,
+    /// Balance not owed to any survey or recipient
+    pub surplus: u128,
+}
+
+#[cw_serde]
+pub struct OwnershipResponse {
+    /// `None` once ownership has been renounced
+    pub owner: Option<String>,
+    pub pending_owner: Option<String>,
+    pub pending_expiry: Option<u64>,
+}
+
+#[cw_serde]
+pub struct SigningDomainResponse {
+    pub version: DomainVersion,
+    pub domain: String,
+    pub enabled: bool,
+}
@@ -0,0 +1,361 @@
+use crate::helpers;
+use crate::msg::{
+    OwnershipResponse, ParticipantRewardResponse, Route, SignaturePolicy, SigningDomainResponse,
+    SolvencyResponse, SurveyResponse,
+};
+use crate::state::{
+    survey_rewarded_users, surveys, CancelSurveyPayload, Config, CreateSurveyPayload,
+    DomainVersion, FundSurveyPayload, PayRewardsPayload, ProofEnvelope, SigningDomain, SurveyInfo,
+    SurveyStatus, CONFIG, LIABILITIES, PENDING_OWNER, ROUTES, SIGNATURE_POLICIES,
+    USED_PROOF_TOKENS,
+};
+
+use cosmwasm_std::{to_json_binary, Addr, Binary, Deps, Env, Order, StdError, StdResult};
+use cw_storage_plus::{Bound, Map};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+// Pagination bounds for list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+fn signing_domain<'a>(env: &'a Env, version: &DomainVersion) -> SigningDomain<'a> {
+    SigningDomain {
+        name: version.as_str(),
+        chain_id: &env.block.chain_id,
+        contract: env.contract.address.as_str(),
+    }
+}
+
+// Hashes `payload` in the proof schema of `version`
+fn proof_hash<T: Serialize>(
+    version: &DomainVersion,
+    action: &str,
+    payload: &T,
+) -> StdResult<Binary> {
+    let bytes = match version {
+        DomainVersion::V1 => to_json_binary(payload)?,
+        DomainVersion::V2 => to_json_binary(&ProofEnvelope { action, payload })?,
+    };
+    let digest = Sha256::digest(&bytes);
+
+    Ok(Binary::from(digest.to_vec()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_survey_proof(
+    env: &Env,
+    version: &DomainVersion,
+    token: &str,
+    time_to_expire: u64,
+    owner: &str,
+    survey_id: &str,
+    participants_limit: u32,
+    reward_amount: u128,
+    survey_hash: &str,
+    reward_denom: &str,
+    ends_at: Option<u64>,
+) -> StdResult<Binary> {
+    let payload = CreateSurveyPayload {
+        token,
+        time_to_expire,
+        owner,
+        survey_id,
+        participants_limit,
+        reward_amount,
+        survey_hash,
+        reward_denom,
+        ends_at,
+        domain: signing_domain(env, version),
+    };
+
+    proof_hash(version, "create_survey", &payload)
+}
+
+pub fn cancel_survey_proof(
+    env: &Env,
+    version: &DomainVersion,
+    token: &str,
+    time_to_expire: u64,
+    survey_id: &str,
+) -> StdResult<Binary> {
+    let payload = CancelSurveyPayload {
+        token,
+        time_to_expire,
+        survey_id,
+        domain: signing_domain(env, version),
+    };
+
+    proof_hash(version, "cancel_survey", &payload)
+}
+
+pub fn fund_survey_proof(
+    env: &Env,
+    version: &DomainVersion,
+    token: &str,
+    time_to_expire: u64,
+    survey_id: &str,
+    additional_participants: u32,
+) -> StdResult<Binary> {
+    let payload = FundSurveyPayload {
+        token,
+        time_to_expire,
+        survey_id,
+        additional_participants,
+        domain: signing_domain(env, version),
+    };
+
+    proof_hash(version, "fund_survey", &payload)
+}
+
+pub fn pay_rewards_proof(
+    env: &Env,
+    version: &DomainVersion,
+    token: &str,
+    time_to_expire: u64,
+    survey_id: Vec<String>,
+    participants: Vec<String>,
+) -> StdResult<Binary> {
+    let payload = PayRewardsPayload {
+        token,
+        time_to_expire,
+        survey_id,
+        participants,
+        domain: signing_domain(env, version),
+    };
+
+    proof_hash(version, "pay_rewards", &payload)
+}
+
+pub fn get_survey(deps: Deps, survey_id: &str) -> StdResult<SurveyResponse> {
+    let survey_info = surveys().load(deps.storage, survey_id)?;
+    Ok(SurveyResponse::new(survey_id.to_string(), survey_info))
+}
+
+/// Lists surveys ordered by id. The creator or status filter is served from its index,
+/// any remaining filters are applied while paging.
+pub fn list_surveys(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    creator: Option<String>,
+    denom: Option<String>,
+    status: Option<SurveyStatus>,
+) -> StdResult<Vec<SurveyResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // Creators are usually remote accounts, stored unchecked
+    let creator = creator
+        .map(|creator| {
+            helpers::lookup_account(deps, &creator)
+                .map_err(|err| StdError::generic_err(err.to_string()))
+        })
+        .transpose()?;
+
+    let surveys = surveys();
+    let iter: Box<dyn Iterator<Item = StdResult<(String, SurveyInfo)>>> = match (&creator, &status)
+    {
+        (Some(creator), _) => surveys.idx.creator.prefix(creator.clone()).range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        ),
+        (None, Some(status)) => surveys
+            .idx
+            .status
+            .prefix(status.as_str().to_string())
+            .range(
+                deps.storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            ),
+        (None, None) => surveys.range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        ),
+    };
+
+    iter.filter(|item| match item {
+        Ok((_, survey_info)) => {
+            creator
+                .as_ref()
+                .is_none_or(|creator| survey_info.survey_creator == creator)
+                && status
+                    .as_ref()
+                    .is_none_or(|status| survey_info.status() == *status)
+                && denom
+                    .as_ref()
+                    .is_none_or(|denom| survey_info.reward_denom == *denom)
+        }
+        Err(_) => true,
+    })
+    .take(limit)
+    .map(|item| item.map(|(survey_id, survey_info)| SurveyResponse::new(survey_id, survey_info)))
+    .collect()
+}
+
+pub fn get_survey_amount_to_fund(deps: Deps, survey_id: &str) -> StdResult<u128> {
+    surveys()
+        .load(deps.storage, survey_id)
+        .map(|survey_info| survey_info.participants_limit as u128 * survey_info.reward_amount)
+}
+
+pub fn get_survey_rewards_amount_paid(deps: Deps, survey_id: &str) -> StdResult<u128> {
+    surveys()
+        .load(deps.storage, survey_id)
+        .map(|survey_info| survey_info.participants_rewarded as u128 * survey_info.reward_amount)
+}
+
+pub fn get_solvency(deps: Deps, env: &Env, denom: String) -> StdResult<SolvencyResponse> {
+    let balance = helpers::query_contract_balance(&deps.querier, &env.contract.address, &denom)?;
+    let liabilities = LIABILITIES
+        .may_load(deps.storage, &denom)?
+        .unwrap_or_default();
+
+    Ok(SolvencyResponse {
+        denom,
+        balance: balance.u128(),
+        liabilities,
+        surplus: balance.u128().saturating_sub(liabilities),
+    })
+}
+
+pub fn list_routes(deps: Deps) -> StdResult<Vec<Route>> {
+    ROUTES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, route)| route))
+        .collect()
+}
+
+pub fn list_signature_policies(deps: Deps) -> StdResult<Vec<SignaturePolicy>> {
+    SIGNATURE_POLICIES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, policy)| policy))
+        .collect()
+}
+
+pub fn list_signing_domains(deps: Deps) -> StdResult<Vec<SigningDomainResponse>> {
+    DomainVersion::ALL
+        .into_iter()
+        .map(|version| {
+            Ok(SigningDomainResponse {
+                enabled: helpers::is_domain_enabled(deps.storage, &version)?,
+                domain: version.as_str().to_string(),
+                version,
+            })
+        })
+        .collect()
+}
+
+pub fn is_proof_token_used(deps: Deps, token: &str) -> StdResult<bool> {
+    Ok(USED_PROOF_TOKENS.has(deps.storage, token))
+}
+
+/// Payouts parked in `recoveries` for `addr` after a failed transfer
+pub fn get_recoveries<T>(
+    deps: Deps,
+    recoveries: &Map<&Addr, Vec<T>>,
+    addr: &str,
+) -> StdResult<Vec<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let addr = helpers::lookup_account(deps, addr)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    Ok(recoveries
+        .may_load(deps.storage, &addr)?
+        .unwrap_or_default())
+}
+
+pub fn get_config(deps: Deps) -> StdResult<Config> {
+    CONFIG.load(deps.storage)
+}
+
+pub fn get_ownership(deps: Deps) -> StdResult<OwnershipResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let pending_owner = PENDING_OWNER.may_load(deps.storage)?;
+
+    Ok(OwnershipResponse {
+        owner: config.owner.map(|owner| owner.to_string()),
+        pending_expiry: pending_owner
+            .as_ref()
+            .and_then(|pending| pending.expires_at),
+        pending_owner: pending_owner.map(|pending| pending.owner.to_string()),
+    })
+}
+
+pub fn get_has_claimed_reward(deps: Deps, survey_id: &str, participant: &str) -> StdResult<bool> {
+    let participant = helpers::lookup_account(deps, participant)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let already_rewarded = survey_rewarded_users().has(deps.storage, (survey_id, &participant));
+
+    Ok(already_rewarded)
+}
+
+/// Lists the participants already rewarded from a survey, ordered by address
+pub fn list_rewarded_participants(
+    deps: Deps,
+    survey_id: &str,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<String>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let start_after = start_after
+        .map(|addr| helpers::lookup_account(deps, &addr))
+        .transpose()
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    survey_rewarded_users()
+        .prefix(survey_id)
+        .keys(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|participant| participant.map(|participant| participant.to_string()))
+        .collect()
+}
+
+/// Lists the surveys a participant has been rewarded from, ordered by survey id
+pub fn list_participant_rewards(
+    deps: Deps,
+    participant: &str,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ParticipantRewardResponse>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let participant = helpers::lookup_account(deps, participant)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    survey_rewarded_users()
+        .idx
+        .participant
+        .prefix(participant.clone())
+        .keys(
+            deps.storage,
+            start_after.map(|survey_id| Bound::exclusive((survey_id, participant))),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|key| {
+            let (survey_id, _) = key?;
+            let survey_info = surveys().load(deps.storage, &survey_id)?;
+            Ok(ParticipantRewardResponse {
+                survey_id,
+                reward_amount: survey_info.reward_amount,
+                reward_denom: survey_info.reward_denom,
+            })
+        })
+        .collect()
+}
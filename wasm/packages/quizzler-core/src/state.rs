@@ -0,0 +1,306 @@
+use crate::msg::{Route, SignaturePolicy};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Binary, Empty, IbcTimeoutBlock};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex};
+use serde::Serialize;
+
+#[cw_serde]
+pub struct ManagerInfo {
+    pub address: Addr,
+    pub pub_key: Binary,
+    #[serde(default)]
+    pub key_type: KeyType,
+    pub status: bool,
+}
+
+/// Signature scheme of a manager key. Managers stored before secp256k1 support are
+/// ed25519.
+#[cw_serde]
+#[derive(Default)]
+pub enum KeyType {
+    #[default]
+    Ed25519,
+    Secp256k1,
+}
+
+impl KeyType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyType::Ed25519 => "ed25519",
+            KeyType::Secp256k1 => "secp256k1",
+        }
+    }
+}
+
+#[cw_serde]
+pub struct Config {
+    /// `None` once ownership has been renounced
+    pub owner: Option<Addr>,
+    /// Seconds after dispatch before an outgoing packet times out
+    pub ibc_timeout_seconds: u64,
+    /// Optional counterparty height after which an outgoing packet times out
+    pub ibc_timeout_block: Option<IbcTimeoutBlock>,
+}
+
+pub const DEFAULT_IBC_TIMEOUT_SECONDS: u64 = 600; // 10 minutes
+
+pub const MAX_IBC_TIMEOUT_SECONDS: u64 = 7 * 24 * 60 * 60; // 1 week
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+/// Owner proposed by the current owner, who becomes owner once they accept
+#[cw_serde]
+pub struct PendingOwner {
+    pub owner: Addr,
+    /// Block time in seconds after which the proposal can no longer be accepted
+    pub expires_at: Option<u64>,
+}
+
+pub const PENDING_OWNER: Item<PendingOwner> = Item::new("pending_owner");
+
+// Route used to reach the accounts of each bech32 prefix
+pub const ROUTES: Map<&str, Route> = Map::new("routes");
+
+// Signatures needed above a value limit, per denom
+pub const SIGNATURE_POLICIES: Map<&str, SignaturePolicy> = Map::new("signature_policies");
+
+// Managers
+pub struct ManagerIndexes<'a> {
+    pub pub_key: UniqueIndex<'a, Vec<u8>, ManagerInfo, Addr>,
+}
+
+impl IndexList<ManagerInfo> for ManagerIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ManagerInfo>> + '_> {
+        let v: Vec<&dyn Index<ManagerInfo>> = vec![&self.pub_key];
+        Box::new(v.into_iter())
+    }
+}
+
+// Managers, indexed by public key so a signer is found without scanning every manager.
+// A public key can only belong to one manager
+pub fn managers<'a>() -> IndexedMap<&'a Addr, ManagerInfo, ManagerIndexes<'a>> {
+    let indexes = ManagerIndexes {
+        pub_key: UniqueIndex::new(|manager| manager.pub_key.to_vec(), "managers__pub_key"),
+    };
+    IndexedMap::new("managers", indexes)
+}
+
+#[cw_serde]
+pub struct SurveyInfo {
+    pub survey_creator: Addr,
+    pub participants_limit: u32,
+    pub reward_amount: u128,
+    pub participants_rewarded: u32,
+    pub survey_hash: String,
+    pub is_cancelled: bool,
+    pub reward_denom: String,
+    /// Block time (seconds) after which rewards can no longer be paid and anyone can
+    /// close the survey to refund the creator
+    pub ends_at: Option<u64>,
+    /// Total amount deposited for this survey, refunds are computed from it
+    pub funded_amount: u128,
+}
+
+#[cw_serde]
+pub enum SurveyStatus {
+    Active,
+    Completed,
+    Cancelled,
+}
+
+impl SurveyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SurveyStatus::Active => "active",
+            SurveyStatus::Completed => "completed",
+            SurveyStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl SurveyInfo {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.ends_at.is_some_and(|ends_at| now >= ends_at)
+    }
+
+    pub fn status(&self) -> SurveyStatus {
+        if self.is_cancelled {
+            SurveyStatus::Cancelled
+        } else if self.participants_rewarded >= self.participants_limit {
+            SurveyStatus::Completed
+        } else {
+            SurveyStatus::Active
+        }
+    }
+}
+
+pub struct SurveyIndexes<'a> {
+    pub creator: MultiIndex<'a, Addr, SurveyInfo, String>,
+    pub status: MultiIndex<'a, String, SurveyInfo, String>,
+}
+
+impl IndexList<SurveyInfo> for SurveyIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<SurveyInfo>> + '_> {
+        let v: Vec<&dyn Index<SurveyInfo>> = vec![&self.creator, &self.status];
+        Box::new(v.into_iter())
+    }
+}
+
+// Surveys, indexed by creator and status. Every write to a survey must go through
+// this map so the indexes stay in sync
+pub fn surveys<'a>() -> IndexedMap<&'a str, SurveyInfo, SurveyIndexes<'a>> {
+    let indexes = SurveyIndexes {
+        creator: MultiIndex::new(
+            |_pk, survey| survey.survey_creator.clone(),
+            "surveys",
+            "surveys__creator",
+        ),
+        status: MultiIndex::new(
+            |_pk, survey| survey.status().as_str().to_string(),
+            "surveys",
+            "surveys__status",
+        ),
+    };
+    IndexedMap::new("surveys", indexes)
+}
+
+#[cw_serde]
+pub struct RewardedUser {
+    pub participant: Addr,
+}
+
+pub struct RewardedUserIndexes<'a> {
+    pub participant: MultiIndex<'a, Addr, RewardedUser, (String, Addr)>,
+}
+
+impl IndexList<RewardedUser> for RewardedUserIndexes<'_> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<RewardedUser>> + '_> {
+        let v: Vec<&dyn Index<RewardedUser>> = vec![&self.participant];
+        Box::new(v.into_iter())
+    }
+}
+
+// Survey rewarded users, keyed by (survey id, participant) and indexed by participant
+pub fn survey_rewarded_users<'a>(
+) -> IndexedMap<(&'a str, &'a Addr), RewardedUser, RewardedUserIndexes<'a>> {
+    let indexes = RewardedUserIndexes {
+        participant: MultiIndex::new(
+            |_pk, rewarded| rewarded.participant.clone(),
+            "survey_rewarded_users",
+            "survey_rewarded_users__participant",
+        ),
+    };
+    IndexedMap::new("survey_rewarded_users", indexes)
+}
+
+// Committed but unpaid funds per denom: unspent survey funding plus payouts parked
+// for recovery. Only the balance above this is free.
+pub const LIABILITIES: Map<&str, u128> = Map::new("liabilities");
+
+// Used proof tokens and the `time_to_expire` of the proof they were used with
+pub const USED_PROOF_TOKENS: Map<&str, u64> = Map::new("used_proof_tokens");
+
+// Used proof tokens bucketed by expiry, so expired ones can be pruned in order
+pub const PROOF_TOKEN_EXPIRIES: Map<(u64, &str), Empty> = Map::new("proof_token_expiries");
+
+pub const PROOF_TOKEN_BUCKET_SECONDS: u64 = 60 * 60; // 1 hour
+
+// Furthest in the future a proof may expire, bounding how long a token is kept
+pub const MAX_PROOF_WINDOW_SECONDS: u64 = 24 * 60 * 60; // 1 day
+
+pub fn proof_token_bucket(time_to_expire: u64) -> u64 {
+    time_to_expire / PROOF_TOKEN_BUCKET_SECONDS
+}
+
+/// Version of the proof payloads managers sign. A change to a payload schema ships as a
+/// new version, which the owner enables next to the current one and retires the old one
+/// from once every signer has moved over.
+#[cw_serde]
+#[derive(Default)]
+pub enum DomainVersion {
+    /// The payload itself is signed
+    #[default]
+    V1,
+    /// The payload is wrapped in a `ProofEnvelope` naming the operation it authorizes
+    V2,
+}
+
+impl DomainVersion {
+    pub const ALL: [DomainVersion; 2] = [DomainVersion::V1, DomainVersion::V2];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DomainVersion::V1 => "SURVEY_V1",
+            DomainVersion::V2 => "SURVEY_V2",
+        }
+    }
+
+    /// Whether the version is accepted before the owner enables or retires it, so
+    /// existing deployments keep accepting V1 proofs after an upgrade and V2 is only
+    /// accepted once the owner enables it
+    pub fn enabled_by_default(&self) -> bool {
+        matches!(self, DomainVersion::V1)
+    }
+}
+
+/// V2 proof schema. Naming the operation keeps a signature from ever authorizing another
+/// operation whose payload happens to serialize the same way.
+#[derive(Serialize)]
+pub struct ProofEnvelope<'a, T> {
+    pub action: &'a str,
+    pub payload: &'a T,
+}
+
+// Signing domain versions the owner has enabled (`true`) or retired (`false`)
+pub const SIGNING_DOMAINS: Map<&str, bool> = Map::new("signing_domains");
+
+/// Signed with every proof so it is only valid for one contract on one chain
+#[cw_serde]
+pub struct SigningDomain<'a> {
+    pub name: &'a str,
+    pub chain_id: &'a str,
+    pub contract: &'a str,
+}
+
+#[cw_serde]
+pub struct CreateSurveyPayload<'a> {
+    pub token: &'a str,
+    pub time_to_expire: u64,
+    pub owner: &'a str,
+    pub survey_id: &'a str,
+    pub participants_limit: u32,
+    pub reward_amount: u128,
+    pub survey_hash: &'a str,
+    pub reward_denom: &'a str,
+    // Left out of the signed bytes when unset, so proofs for surveys without a deadline
+    // are unchanged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<u64>,
+    pub domain: SigningDomain<'a>,
+}
+
+#[cw_serde]
+pub struct CancelSurveyPayload<'a> {
+    pub token: &'a str,
+    pub time_to_expire: u64,
+    pub survey_id: &'a str,
+    pub domain: SigningDomain<'a>,
+}
+
+#[cw_serde]
+pub struct FundSurveyPayload<'a> {
+    pub token: &'a str,
+    pub time_to_expire: u64,
+    pub survey_id: &'a str,
+    pub additional_participants: u32,
+    pub domain: SigningDomain<'a>,
+}
+
+#[cw_serde]
+pub struct PayRewardsPayload<'a> {
+    pub token: &'a str,
+    pub time_to_expire: u64,
+    pub survey_id: Vec<String>,
+    pub participants: Vec<String>,
+    pub domain: SigningDomain<'a>,
+}
@@ -0,0 +1,134 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, BankMsg, Coin, Deps, DepsMut, Env, Storage, SubMsg, Uint128};
+
+use crate::error::ContractError;
+use crate::helpers;
+use crate::msg::Route;
+use crate::state::LIABILITIES;
+
+/// Why funds are leaving the contract.
+#[cw_serde]
+pub enum TransferKind {
+    /// Reward paid out to a survey participant
+    Reward,
+    /// Unspent survey funding returned to the survey creator
+    Refund,
+}
+
+/// Where a recipient account lives
+#[cw_serde]
+pub enum Destination {
+    /// An account of the chain the contract runs on, paid with a bank send
+    Local,
+    /// An account on another chain, paid over IBC along `route`
+    Remote { route: Route },
+}
+
+/// An outgoing payment requested by the survey logic.
+#[cw_serde]
+pub struct Transfer {
+    pub kind: TransferKind,
+    pub survey_id: String,
+    pub recipient: Addr,
+    pub coin: Coin,
+}
+
+/// Chain specific glue turning a [`Transfer`] into a message the host chain can dispatch.
+///
+/// `route` is the route resolved for the recipient by `send_transfer`. Implementations
+/// are free to record whatever they need to track the packet through its IBC lifecycle,
+/// which is why they receive mutable access to the contract state.
+pub trait TransferAdapter {
+    fn create_transfer(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        transfer: Transfer,
+        route: Route,
+    ) -> Result<SubMsg, ContractError>;
+}
+
+/// Adds `amount` to the liabilities of `denom`, for funds the contract now owes to a
+/// survey or a recipient.
+pub fn record_liability(
+    storage: &mut dyn Storage,
+    denom: &str,
+    amount: u128,
+) -> Result<(), ContractError> {
+    LIABILITIES.update(storage, denom, |liabilities| {
+        liabilities
+            .unwrap_or_default()
+            .checked_add(amount)
+            .ok_or(ContractError::ArithmeticError {})
+    })?;
+    Ok(())
+}
+
+/// Removes `amount` from the liabilities of `denom`, for funds that have left the contract
+pub fn release_liability(
+    storage: &mut dyn Storage,
+    denom: &str,
+    amount: u128,
+) -> Result<(), ContractError> {
+    LIABILITIES.update(storage, denom, |liabilities| {
+        liabilities
+            .unwrap_or_default()
+            .checked_sub(amount)
+            .ok_or(ContractError::LiabilitiesExceeded {})
+    })?;
+    Ok(())
+}
+
+/// Checks that `amount` of `denom` is covered by the liabilities ledger and that the
+/// contract balance covers every liability of that denom. Returns the current liabilities.
+pub fn ensure_solvent(
+    deps: Deps,
+    env: &Env,
+    denom: &str,
+    amount: u128,
+) -> Result<u128, ContractError> {
+    let liabilities = LIABILITIES
+        .may_load(deps.storage, denom)?
+        .unwrap_or_default();
+
+    if amount > liabilities {
+        return Err(ContractError::LiabilitiesExceeded {});
+    }
+
+    let balance = helpers::query_contract_balance(&deps.querier, &env.contract.address, denom)?;
+
+    if balance < Uint128::from(liabilities) {
+        return Err(ContractError::InsufficientContractBalance {});
+    }
+
+    Ok(liabilities)
+}
+
+/// Dispatches `transfer` once the contract is known to be able to cover it, releasing the
+/// amount from the liabilities ledger. Recipients on the host chain are paid with a plain
+/// bank send, everyone else through `adapter`.
+///
+/// Every payout must go through here so the ledger matches the funds that are owed.
+pub fn send_transfer(
+    deps: DepsMut,
+    env: &Env,
+    adapter: &impl TransferAdapter,
+    transfer: Transfer,
+) -> Result<SubMsg, ContractError> {
+    let denom = transfer.coin.denom.clone();
+    let amount = transfer.coin.amount.u128();
+
+    let liabilities = ensure_solvent(deps.as_ref(), env, &denom, amount)?;
+    LIABILITIES.save(deps.storage, &denom, &(liabilities - amount))?;
+
+    let (destination, recipient) =
+        helpers::validate_account(deps.as_ref(), transfer.recipient.as_str())?;
+
+    match destination {
+        Destination::Local => Ok(SubMsg::new(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![transfer.coin],
+        })),
+        Destination::Remote { route } => adapter.create_transfer(deps, env, transfer, route),
+    }
+}